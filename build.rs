@@ -0,0 +1,158 @@
+//! Assembles the runtime code of the contracts installed by the lens
+//!
+//! `contracts/*.easm` are the sources of the proxy and forwarder code, the assembled code
+//! is written to `$OUT_DIR/<name>.bin` and embedded by `src/contract.rs`.
+//! The syntax is described at the top of `contracts/Proxy.easm`.
+
+use std::{collections::HashMap, env, fs, path::Path};
+
+const CONTRACTS: [&str; 2] = ["Proxy", "Forwarder"];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+
+    for name in CONTRACTS {
+        let path = format!("contracts/{name}.easm");
+        println!("cargo:rerun-if-changed={path}");
+
+        let source = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{path}: {err}"));
+        let code = assemble(&source).unwrap_or_else(|err| panic!("{path}: {err}"));
+        fs::write(Path::new(&out_dir).join(format!("{name}.bin")), code).expect("OUT_DIR is writable");
+    }
+}
+
+/// A token of the source once constants are resolved
+enum Item<'a> {
+    Opcode(u8),
+    Push(Vec<u8>),
+    Label(&'a str),
+    LabelRef(&'a str),
+}
+
+impl Item<'_> {
+    fn size(&self) -> usize {
+        match self {
+            Self::Opcode(_) | Self::Label(_) => 1,
+            Self::Push(value) => 1 + value.len(),
+            Self::LabelRef(_) => 3,
+        }
+    }
+}
+
+fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut constants = HashMap::new();
+    let mut items = vec![];
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if let [name, "=", value] = tokens.as_slice() {
+            let value = literal(value).ok_or_else(|| format!("line {}: invalid constant `{value}`", number + 1))?;
+            if constants.insert(*name, value).is_some() {
+                return Err(format!("line {}: duplicate constant `{name}`", number + 1));
+            }
+            continue;
+        }
+
+        for token in tokens {
+            let item = if let Some(label) = token.strip_suffix(':') {
+                Item::Label(label)
+            } else if let Some(label) = token.strip_prefix('@') {
+                Item::LabelRef(label)
+            } else if let Some(value) = constants.get(token).cloned().or_else(|| literal(token)) {
+                Item::Push(value)
+            } else {
+                Item::Opcode(opcode(token).ok_or_else(|| format!("line {}: unknown token `{token}`", number + 1))?)
+            };
+            items.push(item);
+        }
+    }
+
+    let mut labels = HashMap::new();
+    let mut offset = 0;
+    for item in &items {
+        if let Item::Label(label) = item
+            && labels.insert(*label, offset).is_some()
+        {
+            return Err(format!("duplicate label `{label}`"));
+        }
+        offset += item.size();
+    }
+
+    let mut code = Vec::with_capacity(offset);
+    for item in &items {
+        match item {
+            Item::Opcode(opcode) => code.push(*opcode),
+            Item::Push(value) => {
+                code.push(0x5f + value.len() as u8);
+                code.extend(value);
+            }
+            Item::Label(_) => code.push(0x5b),
+            Item::LabelRef(label) => {
+                let offset: u16 = labels.get(label)
+                    .ok_or_else(|| format!("unknown label `{label}`"))?
+                    .to_owned()
+                    .try_into()
+                    .map_err(|_| format!("label `{label}` out of PUSH2 range"))?;
+                code.push(0x61);
+                code.extend(offset.to_be_bytes());
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+/// Big endian bytes of a decimal or `0x` literal without leading zeros, at most 32 of them
+fn literal(token: &str) -> Option<Vec<u8>> {
+    let bytes = match token.strip_prefix("0x") {
+        Some(digits) => {
+            let digits = if digits.len() % 2 == 1 { format!("0{digits}") } else { digits.to_string() };
+            (0..digits.len())
+                .step_by(2)
+                .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+                .collect::<Option<Vec<u8>>>()?
+        }
+        None if token.bytes().all(|elt| elt.is_ascii_digit()) => token.parse::<u128>().ok()?.to_be_bytes().to_vec(),
+        None => return None,
+    };
+
+    let bytes: Vec<u8> = bytes.into_iter().skip_while(|elt| *elt == 0).collect();
+    (bytes.len() <= 32).then_some(bytes)
+}
+
+fn opcode(mnemonic: &str) -> Option<u8> {
+    let numbered = |prefix: &str, base: u8, max: u8| {
+        mnemonic.strip_prefix(prefix)
+            .and_then(|elt| elt.parse::<u8>().ok())
+            .filter(|elt| (1..=max).contains(elt))
+            .map(|elt| base + elt)
+    };
+    // PUSH1 to PUSH32 are only emitted for literals
+    if let Some(opcode) = numbered("DUP", 0x7f, 16).or_else(|| numbered("SWAP", 0x8f, 16)) {
+        return Some(opcode);
+    }
+
+    let opcode = match mnemonic {
+        "STOP" => 0x00, "ADD" => 0x01, "MUL" => 0x02, "SUB" => 0x03, "DIV" => 0x04, "SDIV" => 0x05,
+        "MOD" => 0x06, "SMOD" => 0x07, "ADDMOD" => 0x08, "MULMOD" => 0x09, "EXP" => 0x0a, "SIGNEXTEND" => 0x0b,
+        "LT" => 0x10, "GT" => 0x11, "SLT" => 0x12, "SGT" => 0x13, "EQ" => 0x14, "ISZERO" => 0x15,
+        "AND" => 0x16, "OR" => 0x17, "XOR" => 0x18, "NOT" => 0x19, "BYTE" => 0x1a, "SHL" => 0x1b,
+        "SHR" => 0x1c, "SAR" => 0x1d, "KECCAK256" => 0x20,
+        "ADDRESS" => 0x30, "BALANCE" => 0x31, "ORIGIN" => 0x32, "CALLER" => 0x33, "CALLVALUE" => 0x34,
+        "CALLDATALOAD" => 0x35, "CALLDATASIZE" => 0x36, "CALLDATACOPY" => 0x37, "CODESIZE" => 0x38,
+        "CODECOPY" => 0x39, "GASPRICE" => 0x3a, "EXTCODESIZE" => 0x3b, "EXTCODECOPY" => 0x3c,
+        "RETURNDATASIZE" => 0x3d, "RETURNDATACOPY" => 0x3e, "EXTCODEHASH" => 0x3f,
+        "BLOCKHASH" => 0x40, "COINBASE" => 0x41, "TIMESTAMP" => 0x42, "NUMBER" => 0x43,
+        "PREVRANDAO" => 0x44, "GASLIMIT" => 0x45, "CHAINID" => 0x46, "SELFBALANCE" => 0x47, "BASEFEE" => 0x48,
+        "POP" => 0x50, "MLOAD" => 0x51, "MSTORE" => 0x52, "MSTORE8" => 0x53, "SLOAD" => 0x54,
+        "SSTORE" => 0x55, "JUMP" => 0x56, "JUMPI" => 0x57, "PC" => 0x58, "MSIZE" => 0x59, "GAS" => 0x5a,
+        "JUMPDEST" => 0x5b, "TLOAD" => 0x5c, "TSTORE" => 0x5d, "MCOPY" => 0x5e, "PUSH0" => 0x5f,
+        "CREATE" => 0xf0, "CALL" => 0xf1, "CALLCODE" => 0xf2, "RETURN" => 0xf3, "DELEGATECALL" => 0xf4,
+        "CREATE2" => 0xf5, "STATICCALL" => 0xfa, "REVERT" => 0xfd, "INVALID" => 0xfe,
+        _ => return None,
+    };
+
+    Some(opcode)
+}
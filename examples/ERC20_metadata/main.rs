@@ -20,19 +20,23 @@ sol! {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
-    let rpc_url = env::var("RPC_URL").unwrap();
+    let rpc_url = env::var("RPC_URL")?;
     let ws = WsConnect::new(rpc_url);
-    let provider = ProviderBuilder::new().connect_ws(ws).await.unwrap();
+    let provider = ProviderBuilder::new().connect_ws(ws).await?;
 
     let lens_address = Address::repeat_byte(0xca);
 
-    let result= Lens::new(&provider)
+    let results = Lens::new(&provider)
         .with_ephemeral(&lens_address, ITokenLens::DEPLOYED_BYTECODE.clone())
         .with_call::<ITokenLens::getTokenCall>(&lens_address, (address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),))
         .with_call::<ITokenLens::getTokenCall>(&lens_address, (address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),))
-        .call().await;
+        .call().await?;
 
-    println!("{:?}", result);
+    for result in results {
+        println!("{:?}", result.result);
+    }
+
+    Ok(())
 }
\ No newline at end of file
@@ -50,20 +50,24 @@ sol! {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
-    let rpc_url = env::var("RPC_URL").unwrap();
+    let rpc_url = env::var("RPC_URL")?;
     let ws = WsConnect::new(rpc_url);
-    let provider = ProviderBuilder::new().connect_ws(ws).await.unwrap();
+    let provider = ProviderBuilder::new().connect_ws(ws).await?;
 
     let pool_manager_address = address!("0x000000000004444c5dc75cB358380D2e3dE08A90");
 
-    let result= Lens::new(&provider)
+    let results = Lens::new(&provider)
         // The registry override deployed at the pool manager address
         .with_ephemeral(&pool_manager_address, IRegistryOverride::DEPLOYED_BYTECODE.clone())
         .with_call::<IRegistryOverride::getStateCall>(&pool_manager_address, (FixedBytes::from_hex("0x21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27").unwrap(),))
         .with_call::<IRegistryOverride::getStateCall>(&pool_manager_address, (FixedBytes::from_hex("0xccc8eec61db9eac7106cc110b6834c2f9539ea7dd8df139e57587bcb1a701611").unwrap(),))
-        .call().await;
+        .call().await?;
 
-    println!("{:?}", result);
+    for result in results {
+        println!("{:?}", result.result);
+    }
+
+    Ok(())
 }
\ No newline at end of file
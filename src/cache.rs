@@ -0,0 +1,150 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use alloy::{
+    primitives::{map::B256HashMap, Bytes, Keccak256, B256},
+    rpc::types::{state::StateOverride, BlockOverrides},
+};
+
+
+/// Identifies an `eth_call` whose output is deterministic
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey {
+    /// Hash of the block the call is executed at
+    pub block_hash: B256,
+    /// Calldata sent to the proxy, or to Multicall3
+    pub calldata: Bytes,
+    /// Hash of the state and block overrides sent with the call
+    pub overrides: B256,
+}
+
+impl CacheKey {
+    /// Key of the call sending `calldata` with `state_overrides` and `block_overrides` at `block_hash`
+    pub fn new(block_hash: B256, calldata: Bytes, state_overrides: &StateOverride, block_overrides: Option<&BlockOverrides>) -> Self {
+        Self { block_hash, calldata, overrides: overrides_hash(state_overrides, block_overrides) }
+    }
+}
+
+/// Store of raw `eth_call` outputs, set with [`Lens::with_cache`](crate::Lens::with_cache)
+///
+/// Implement it to keep outputs in a custom store, e.g. an embedded database or a file
+pub trait LensCache: Send + Sync {
+    /// Output stored for `key`, if any
+    fn get(&self, key: &CacheKey) -> Option<Bytes>;
+
+    /// Stores the `output` of the call identified by `key`
+    fn insert(&self, key: CacheKey, output: Bytes);
+}
+
+/// In-memory [`LensCache`] evicting the least recently used output once full
+///
+/// # Example
+/// ```
+/// # use std::sync::Arc;
+/// # use alloy_ephemeral_lens::{Lens, LruCache};
+/// # use alloy::providers::ProviderBuilder;
+/// # tokio_test::block_on(async {
+/// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+/// let cache = Arc::new(LruCache::new(1_000));
+///
+/// let mut lens = Lens::new(&provider);
+/// lens.with_cache(cache.clone());
+/// # })
+/// ```
+#[derive(Debug)]
+pub struct LruCache {
+    /// Maximum number of stored outputs
+    capacity: usize,
+    entries: Mutex<LruEntries>,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    /// Outputs with the tick they were last used at
+    outputs: HashMap<CacheKey, (Bytes, u64)>,
+    /// Keys by the tick they were last used at, least recent first
+    recency: BTreeMap<u64, CacheKey>,
+    /// Incremented on each use
+    tick: u64,
+}
+
+impl LruCache {
+    /// Constructs an empty cache holding up to `capacity` outputs
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, entries: Mutex::default() }
+    }
+
+    /// Number of stored outputs
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().outputs.len()
+    }
+
+    /// Whether no output is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl LensCache for LruCache {
+    fn get(&self, key: &CacheKey) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        let (output, used) = entries.outputs.get_mut(key)?;
+        let output = output.clone();
+        let previous = std::mem::replace(used, tick);
+        entries.recency.remove(&previous);
+        entries.recency.insert(tick, key.clone());
+
+        Some(output)
+    }
+
+    fn insert(&self, key: CacheKey, output: Bytes) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        if let Some((_, used)) = entries.outputs.insert(key.clone(), (output, tick)) {
+            entries.recency.remove(&used);
+        } else if entries.outputs.len() > self.capacity
+            && let Some((_, evicted)) = entries.recency.pop_first()
+        {
+            entries.outputs.remove(&evicted);
+        }
+        entries.recency.insert(tick, key);
+    }
+}
+
+/// Hash of the overrides, independent of the iteration order of their maps
+fn overrides_hash(state_overrides: &StateOverride, block_overrides: Option<&BlockOverrides>) -> B256 {
+    let mut accounts: Vec<_> = state_overrides.iter().collect();
+    accounts.sort_by_key(|(address, _)| **address);
+
+    let mut hasher = Keccak256::new();
+    for (address, account) in accounts {
+        hasher.update(address);
+        hasher.update(serde_json::to_vec(&(
+            account.balance,
+            account.nonce,
+            &account.code,
+            sorted(&account.state),
+            sorted(&account.state_diff),
+            account.move_precompile_to,
+        )).expect("overrides serialize to JSON"));
+    }
+    hasher.update(serde_json::to_vec(&block_overrides).expect("overrides serialize to JSON"));
+
+    hasher.finalize()
+}
+
+/// Storage slots in ascending order
+fn sorted(slots: &Option<B256HashMap<B256>>) -> Option<BTreeMap<B256, B256>> {
+    slots.as_ref().map(|elt| elt.iter().map(|(slot, value)| (*slot, *value)).collect())
+}
@@ -1,53 +1,119 @@
 use alloy::{
-    dyn_abi::DynSolValue,
+    dyn_abi::{self, DynSolValue, FunctionExt},
+    json_abi::Function,
     primitives::{Address, Bytes, U256}
 };
+use serde::{Deserialize, Serialize};
 
 use crate::contract::IProxy::{self};
 
 
 /// Represents a contract call with encoding and decoding functionalities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Call {
-    /// Function pointer for decoding response data
-    decoder: fn(&[u8]) -> Vec<DynSolValue>,
+    /// Called function, used to decode response data
+    function: Function,
     /// Address of the contract being called
     address: Address,
     /// Encoded function arguments
+    #[serde(rename = "calldata")]
     argument: Bytes,
     /// Ether value sent with the call
     value: U256,
     /// Gas limit for the call
     gas: U256,
+    /// Address the call is sent from, the proxy if unset
+    sender: Option<Address>,
 }
 
 impl Call {
 
-    pub fn new(decoder: fn(&[u8]) -> Vec<DynSolValue>, address: Address, argument: Bytes) -> Self {
-        Self { decoder, address, argument, value: U256::ZERO, gas: U256::ZERO }
+    pub fn new(function: Function, address: Address, argument: Bytes) -> Self {
+        Self { function, address, argument, value: U256::ZERO, gas: U256::ZERO, sender: None }
     }
 
-    /// TODO: unused
+    /// Sets the ether value sent with the call, funded from the proxy balance
     pub fn with_value(&mut self, value: U256) -> &mut Self {
         self.value = value;
         self
     }
 
-    /// TODO: unused
+    /// Sets the gas limit of the call, zero forwards all the available gas
     pub fn with_gas(&mut self, gas: U256) -> &mut Self {
         self.gas = gas;
         self
     }
 
+    /// Sets the address the call is sent from
+    ///
+    /// The call is routed through a forwarder installed at `sender`, replacing its code
+    /// for the duration of the batch, so the callee sees `sender` as `msg.sender`.
+    /// Every call of the batch sees a contract at `sender`, see [`Lens::with_sender`](crate::Lens::with_sender)
+    pub fn with_sender(&mut self, sender: Address) -> &mut Self {
+        self.sender = Some(sender);
+        self
+    }
+
     pub(super) fn encode(&self) -> IProxy::CallArgument {
-        IProxy::CallArgument {
-            callee: self.address,
-            argument: self.argument.clone(),
-            value: self.value,
-            gas: self.gas,
+        match self.sender {
+            Some(sender) => IProxy::CallArgument {
+                callee: sender,
+                argument: [self.address.as_slice(), &self.argument].concat().into(),
+                value: self.value,
+                gas: self.gas,
+            },
+            None => IProxy::CallArgument {
+                callee: self.address,
+                argument: self.argument.clone(),
+                value: self.value,
+                gas: self.gas,
+            },
         }
     }
         
-    pub(super) fn decode(&self, data: &[u8]) -> Vec<DynSolValue> {
-        (self.decoder)(data)
+    /// Size of the call once encoded as an `IProxy::CallArgument` array element
+    pub(super) fn encoded_size(&self) -> usize {
+        let argument_len = self.argument.len() + self.sender.map_or(0, |_| Address::len_bytes());
+
+        // offset + (callee, argument offset, value, gas) + argument length + padded argument
+        32 + 4 * 32 + 32 + argument_len.div_ceil(32) * 32
+    }
+
+    /// Gas limit of the call, `default` when the call has none
+    pub(super) fn gas_estimate(&self, default: u64) -> u64 {
+        if self.gas.is_zero() {
+            default
+        } else {
+            self.gas.saturating_to()
+        }
+    }
+
+    /// Ether value sent with the call
+    pub(super) fn value(&self) -> U256 {
+        self.value
+    }
+
+    /// Encoded function call, selector included
+    pub(super) fn argument(&self) -> &Bytes {
+        &self.argument
+    }
+
+    /// Gas limit of the call, zero if unset
+    pub(super) fn gas(&self) -> U256 {
+        self.gas
+    }
+
+    /// Address of the called contract
+    pub(super) fn target(&self) -> Address {
+        self.address
+    }
+
+    /// Address the call is sent from, if impersonated
+    pub(super) fn sender(&self) -> Option<Address> {
+        self.sender
+    }
+
+    pub(super) fn decode(&self, data: &[u8]) -> Result<Vec<DynSolValue>, dyn_abi::Error> {
+        self.function.abi_decode_output(data)
     }
 }
\ No newline at end of file
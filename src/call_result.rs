@@ -1,51 +1,151 @@
 
 
-use alloy::{dyn_abi::{DynSolType, DynSolValue}, primitives::{Bytes, U256}, sol_types::{Revert, SolError}};
+use alloy::{
+    dyn_abi::{self, DynSolType, DynSolValue, FunctionExt},
+    json_abi::{Error, Function},
+    primitives::{Bytes, U256},
+    sol_types::{self, SolCall},
+};
 
-use crate::call::Call;
+use crate::{call::Call, LensError, RevertReason};
 
 
 /// Represents the result of a contract call
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CallResult {
     /// Indicates if the call was successful
     pub success: bool,
-    /// Gas consumed by the call
+    /// Gas consumed by the callee's execution
+    ///
+    /// Excludes the proxy overhead (call cost, account access, value transfer, returned data copy)
+    /// and the transaction intrinsic cost, so it matches the execution gas of a direct transaction
+    /// to the callee. Impersonated calls include the forwarder overhead.
     pub gas_used: U256,
-    /// Decoded return data
+    /// Indicates if the call consumed all its gas, e.g. ran out of gas
+    pub out_of_gas: bool,
+    /// Gas consumed by the callee's execution when run cold, as the only call of a fresh transaction
+    ///
+    /// Set unless the lens uses [`GasMeasurement::Batch`](crate::GasMeasurement::Batch), or the
+    /// measured run didn't succeed or return as the actual one, e.g. a sequential call depending
+    /// on the previous ones
+    pub cold_gas_used: Option<U256>,
+    /// Gas consumed by the callee's execution when run again right after a first run,
+    /// with its accounts and storage slots warm
+    ///
+    /// Set by [`GasMeasurement::ColdAndWarm`](crate::GasMeasurement::ColdAndWarm), along with `cold_gas_used`
+    pub warm_gas_used: Option<U256>,
+    /// Total gas of the `eth_call` the call was part of, intrinsic cost included
+    pub eth_call_gas: U256,
+    /// Decoded return data, empty if the call reverted or its output couldn't be decoded
     pub result: Vec<DynSolValue>,
+    /// Error decoding the return data of a successful call, e.g. the empty output
+    /// of a call to an address without code
+    pub decode_error: Option<dyn_abi::Error>,
     /// Error details if the call reverted
-    pub revert: Option<Revert>,
+    pub revert: Option<RevertReason>,
+    /// Number of the block the call was executed at, if a block was selected
+    pub block_number: Option<u64>,
+    /// Raw return data of the call, the revert data if it reverted
+    pub output: Bytes,
+    /// Index of the provider that produced the result, 0 for the primary one then the fallbacks in order
+    pub provider: usize,
+    /// Number of attempts made to produce the result, across providers
+    pub attempts: usize,
 }
 
 impl CallResult {
-    /// Constructs a CallResult instance from raw response data
-    pub(super) fn from(call: &Call, data: &Bytes) -> Self {
-        let binding = DynSolType::Bytes.abi_decode(&data[4..]).unwrap();
-        let result_data = binding.as_bytes().unwrap();
+    /// Constructs a CallResult instance from the raw proxy envelope of the call at `index`,
+    /// decoding custom errors against `errors`
+    pub(super) fn from(index: usize, call: &Call, data: &Bytes, errors: &[Error]) -> Result<Self, LensError> {
+        let (success, gas_used, out_of_gas, output) = decode_envelope(data)
+            .ok_or_else(|| LensError::MalformedEnvelope { index, data: data.clone() })?;
 
-        let binding = DynSolType::Tuple(
-            vec![DynSolType::Bool, DynSolType::Uint(256), DynSolType::Bytes]
-        ).abi_decode_params(result_data)
-        .unwrap();
-
-        let result_data = binding.as_tuple().unwrap();
-
-        let success = result_data[0].as_bool().unwrap();
-        let gas_used = result_data[1].as_uint().unwrap().0.into();
+        Ok(Self {
+            gas_used,
+            out_of_gas,
+            ..Self::from_output(call, success, output.into(), errors)
+        })
+    }
 
-        let result = if success {
-            call.decode(result_data[2].as_bytes().unwrap())
-        } else {
-            vec![]
+    /// Constructs a CallResult instance from the success flag and raw output of `call`,
+    /// without gas information
+    ///
+    /// An output not matching the function outputs is reported in [`CallResult::decode_error`]
+    pub(super) fn from_output(call: &Call, success: bool, output: Bytes, errors: &[Error]) -> Self {
+        let (result, decode_error) = match success.then(|| call.decode(&output)) {
+            Some(Ok(result)) => (result, None),
+            Some(Err(err)) => (vec![], Some(err)),
+            None => (vec![], None),
         };
 
         let revert = if success {
             None
         } else {
-            Revert::abi_decode(result_data[2].as_bytes().unwrap()).ok()
+            Some(RevertReason::decode(&output, errors))
         };
 
-        Self { success, gas_used, result, revert }
+        Self {
+            success,
+            gas_used: U256::ZERO,
+            out_of_gas: false,
+            cold_gas_used: None,
+            warm_gas_used: None,
+            eth_call_gas: U256::ZERO,
+            result,
+            decode_error,
+            revert,
+            block_number: None,
+            output,
+            provider: 0,
+            attempts: 1,
+        }
+    }
+
+    /// Decodes the raw return data against the outputs of `function`
+    ///
+    /// Useful when the called contract has several output versions, or to decode
+    /// a stored result again. Fails for a reverted call, whose output is revert data.
+    pub fn decode_with(&self, function: &Function) -> Result<Vec<DynSolValue>, dyn_abi::Error> {
+        function.abi_decode_output(&self.output)
+    }
+
+    /// Decodes the raw return data to the return type of the `sol!` call `T`
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::CallResult;
+    /// # use alloy::sol;
+    /// sol! {
+    ///     #[sol(abi)]
+    ///     function decimals() external view returns (uint8);
+    /// }
+    ///
+    /// fn decimals(result: &CallResult) -> Option<u8> {
+    ///     result.decode_as::<decimalsCall>().ok()
+    /// }
+    /// ```
+    pub fn decode_as<T: SolCall>(&self) -> Result<T::Return, sol_types::Error> {
+        T::abi_decode_returns(&self.output)
     }
-}
\ No newline at end of file
+}
+
+/// Unwraps the `Error(bytes)` envelope produced by the proxy `wrapper`
+/// into `(success, gas_used, out_of_gas, output)`
+pub(super) fn decode_envelope(data: &[u8]) -> Option<(bool, U256, bool, Vec<u8>)> {
+    let binding = DynSolType::Bytes.abi_decode(data.get(4..)?).ok()?;
+    let result_data = binding.as_bytes()?;
+
+    let binding = DynSolType::Tuple(
+        vec![DynSolType::Bool, DynSolType::Uint(256), DynSolType::Bool, DynSolType::Bytes]
+    ).abi_decode_params(result_data)
+    .ok()?;
+
+    let result_data = binding.as_tuple()?;
+
+    let success = result_data[0].as_bool()?;
+    let gas_used = result_data[1].as_uint()?.0;
+    let out_of_gas = result_data[2].as_bool()?;
+    let output = result_data[3].as_bytes()?.to_vec();
+
+    Some((success, gas_used, out_of_gas, output))
+}
@@ -1,8 +1,8 @@
 
-use alloy::sol;
+use alloy::{primitives::{address, Address, Bytes}, sol};
 
 sol! {
-    #[sol(rpc, abi, deployed_bytecode="608060405234801561000f575f5ffd5b5060043610610034575f3560e01c80637d8cb9c114610038578063e8030a6a14610068575b5f5ffd5b610052600480360381019061004d919061033f565b610084565b60405161005f91906104b5565b60405180910390f35b610082600480360381019061007d91906104f7565b6101dc565b005b60608282905067ffffffffffffffff8111156100a3576100a261053e565b5b6040519080825280602002602001820160405280156100d657816020015b60608152602001906001900390816100c15790505b5090505f5f90505b838390508110156101d5573073ffffffffffffffffffffffffffffffffffffffff1663e8030a6a8585848181106101185761011761056b565b5b905060200281019061012a91906105a4565b6040518263ffffffff1660e01b815260040161014691906107cc565b5f604051808303815f87803b15801561015d575f5ffd5b505af192505050801561016e575060015b6101c7573d805f811461019c576040519150601f19603f3d011682016040523d82523d5f602084013e6101a1565b606091505b50808383815181106101b6576101b561056b565b5b6020026020010181905250506101c8565b5b80806001019150506100de565b5092915050565b5f5a90505f5f835f0160208101906101f491906107ec565b73ffffffffffffffffffffffffffffffffffffffff1684806020019061021a9190610817565b6040516102289291906108a7565b5f604051808303815f865af19150503d805f8114610261576040519150601f19603f3d011682016040523d82523d5f602084013e610266565b606091505b5091509150815a8461027891906108ec565b8260405160200161028b93929190610990565b6040516020818303038152906040526040517f08c379a00000000000000000000000000000000000000000000000000000000081526004016102cd9190610a1e565b60405180910390fd5b5f5ffd5b5f5ffd5b5f5ffd5b5f5ffd5b5f5ffd5b5f5f83601f8401126102ff576102fe6102de565b5b8235905067ffffffffffffffff81111561031c5761031b6102e2565b5b602083019150836020820283011115610338576103376102e6565b5b9250929050565b5f5f60208385031215610355576103546102d6565b5b5f83013567ffffffffffffffff811115610372576103716102da565b5b61037e858286016102ea565b92509250509250929050565b5f81519050919050565b5f82825260208201905092915050565b5f819050602082019050919050565b5f81519050919050565b5f82825260208201905092915050565b8281835e5f83830152505050565b5f601f19601f8301169050919050565b5f6103f5826103b3565b6103ff81856103bd565b935061040f8185602086016103cd565b610418816103db565b840191505092915050565b5f61042e83836103eb565b905092915050565b5f602082019050919050565b5f61044c8261038a565b6104568185610394565b935083602082028501610468856103a4565b805f5b858110156104a357848403895281516104848582610423565b945061048f83610436565b925060208a0199505060018101905061046b565b50829750879550505050505092915050565b5f6020820190508181035f8301526104cd8184610442565b905092915050565b5f5ffd5b5f608082840312156104ee576104ed6104d5565b5b81905092915050565b5f6020828403121561050c5761050b6102d6565b5b5f82013567ffffffffffffffff811115610529576105286102da565b5b610535848285016104d9565b91505092915050565b7f4e487b71000000000000000000000000000000000000000000000000000000005f52604160045260245ffd5b7f4e487b71000000000000000000000000000000000000000000000000000000005f52603260045260245ffd5b5f5ffd5b5f5ffd5b5f5ffd5b5f823560016080038336030381126105bf576105be610598565b5b80830191505092915050565b5f73ffffffffffffffffffffffffffffffffffffffff82169050919050565b5f6105f4826105cb565b9050919050565b610604816105ea565b811461060e575f5ffd5b50565b5f8135905061061f816105fb565b92915050565b5f6106336020840184610611565b905092915050565b610644816105ea565b82525050565b5f5ffd5b5f5ffd5b5f5ffd5b5f5f8335600160200384360303811261067257610671610652565b5b83810192508235915060208301925067ffffffffffffffff82111561069a5761069961064a565b5b6001820236038313156106b0576106af61064e565b5b509250929050565b828183375f83830152505050565b5f6106d183856103bd565b93506106de8385846106b8565b6106e7836103db565b840190509392505050565b5f819050919050565b610704816106f2565b811461070e575f5ffd5b50565b5f8135905061071f816106fb565b92915050565b5f6107336020840184610711565b905092915050565b610744816106f2565b82525050565b5f6080830161075b5f840184610625565b6107675f86018261063b565b506107756020840184610656565b85830360208701526107888382846106c6565b925050506107996040840184610725565b6107a6604086018261073b565b506107b46060840184610725565b6107c1606086018261073b565b508091505092915050565b5f6020820190508181035f8301526107e4818461074a565b905092915050565b5f60208284031215610801576108006102d6565b5b5f61080e84828501610611565b91505092915050565b5f5f8335600160200384360303811261083357610832610598565b5b80840192508235915067ffffffffffffffff8211156108555761085461059c565b5b602083019250600182023603831315610871576108706105a0565b5b509250929050565b5f81905092915050565b5f61088e8385610879565b935061089b8385846106b8565b82840190509392505050565b5f6108b3828486610883565b91508190509392505050565b7f4e487b71000000000000000000000000000000000000000000000000000000005f52601160045260245ffd5b5f6108f6826106f2565b9150610901836106f2565b9250828203905081811115610919576109186108bf565b5b92915050565b5f8115159050919050565b6109338161091f565b82525050565b610942816106f2565b82525050565b5f82825260208201905092915050565b5f610962826103b3565b61096c8185610948565b935061097c8185602086016103cd565b610985816103db565b840191505092915050565b5f6060820190506109a35f83018661092a565b6109b06020830185610939565b81810360408301526109c28184610958565b9050949350505050565b5f81519050919050565b5f82825260208201905092915050565b5f6109f0826109cc565b6109fa81856109d6565b9350610a0a8185602086016103cd565b610a13816103db565b840191505092915050565b5f6020820190508181035f830152610a3681846109e6565b90509291505056fea2646970667358221220775c28b49a83ccca26529678f4228bb98d07b80155847879fe060f714cfdc98564736f6c634300081b0033")]
+    #[sol(rpc, abi)]
     #[derive(Debug)]
     interface IProxy {
 
@@ -13,6 +13,53 @@ sol! {
             uint256 gas;
         }
   
-        function execute(CallArgument[]) returns (bytes[]);
+        function execute(CallArgument[]) returns (bytes[] results, uint256 gasUsed);
+
+        function executeSequential(CallArgument[]) returns (bytes[] results, uint256 gasUsed);
     }
-}
\ No newline at end of file
+}
+
+sol! {
+    #[sol(rpc)]
+    #[derive(Debug)]
+    interface IMulticall3 {
+
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+/// Address of the canonical Multicall3 deployment, identical on most chains
+pub(crate) const MULTICALL3_ADDRESS: Address = address!("0xcA11bde05977b3631167028862bE2a173976CA11");
+
+/// Runtime code of the proxy, assembled from `contracts/Proxy.easm` by the build script
+pub(crate) const PROXY_BYTECODE: Bytes = Bytes::from_static(include_bytes!(concat!(env!("OUT_DIR"), "/Proxy.bin")));
+
+/// Runtime code of the forwarder, assembled from `contracts/Forwarder.easm` by the build script
+const FORWARDER_BYTECODE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/Forwarder.bin"));
+
+/// `PROXY` placeholder of `contracts/Forwarder.easm`
+const FORWARDER_PROXY_PLACEHOLDER: Address = Address::repeat_byte(0x01);
+
+/// Position of the `PROXY` placeholder in [`FORWARDER_BYTECODE`], after `CALLER` and `PUSH20`
+const FORWARDER_PROXY_OFFSET: usize = 2;
+
+/// Runtime code of the forwarder installed at impersonated senders, forwarding calls from `proxy`
+pub(crate) fn forwarder_bytecode(proxy: &Address) -> Bytes {
+    let mut bytecode = FORWARDER_BYTECODE.to_vec();
+    let placeholder = &mut bytecode[FORWARDER_PROXY_OFFSET..FORWARDER_PROXY_OFFSET + Address::len_bytes()];
+    debug_assert_eq!(placeholder, FORWARDER_PROXY_PLACEHOLDER.as_slice());
+    placeholder.copy_from_slice(proxy.as_slice());
+
+    bytecode.into()
+}
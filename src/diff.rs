@@ -0,0 +1,138 @@
+use alloy::{dyn_abi::DynSolValue, primitives::{Bytes, U256}};
+
+use crate::{CallResult, LensError, RevertReason};
+
+
+/// Differences between two results of the same call
+///
+/// Fields are `None` or empty when unchanged
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallDiff {
+    /// Index of the call in the batch
+    pub index: usize,
+    /// Success flag before and after, if it changed
+    pub success: Option<(bool, bool)>,
+    /// Revert reason before and after, if it changed
+    pub revert: Option<(Option<RevertReason>, Option<RevertReason>)>,
+    /// Raw return data before and after, if it changed, the revert data of a reverted call
+    pub output: Option<(Bytes, Bytes)>,
+    /// Error decoding the return data before and after, if it changed
+    pub decode_error: Option<(Option<String>, Option<String>)>,
+    /// Changed values of the decoded output, when the call succeeded both times
+    ///
+    /// Outputs with a different number of values, e.g. one of them not decoded, are reported
+    /// as a single change at the empty path, from and to the tuple of all the values
+    pub fields: Vec<FieldDiff>,
+    /// Gas consumed by the callee before and after, if it changed
+    pub gas_used: Option<(U256, U256)>,
+}
+
+/// Changed value in the decoded output of a call
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    /// Position of the value in the output, then in the nested tuples and arrays,
+    /// e.g. `[1, 0]` for the first member of the second returned struct
+    pub path: Vec<usize>,
+    /// Value before
+    pub before: DynSolValue,
+    /// Value after
+    pub after: DynSolValue,
+}
+
+impl CallDiff {
+    /// Compares the results of two executions of the same batch, returning the calls that changed
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{CallDiff, CallResult, LensError};
+    /// # fn run(before: Vec<CallResult>, after: Vec<CallResult>) -> Result<(), LensError> {
+    /// for diff in CallDiff::between(&before, &after)? {
+    ///     for field in diff.fields {
+    ///         println!("call {} output {:?}: {:?} -> {:?}", diff.index, field.path, field.before, field.after);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn between(before: &[CallResult], after: &[CallResult]) -> Result<Vec<Self>, LensError> {
+        if before.len() != after.len() {
+            return Err(LensError::ResultCountMismatch { expected: before.len(), actual: after.len() });
+        }
+
+        Ok(
+            before.iter()
+                .zip(after)
+                .enumerate()
+                .map(|(index, (before, after))| Self::new(index, before, after))
+                .filter(|elt| !elt.is_empty())
+                .collect()
+        )
+    }
+
+    /// Differences between the results `before` and `after` of the call at `index`
+    fn new(index: usize, before: &CallResult, after: &CallResult) -> Self {
+        let mut fields = vec![];
+        if before.success && after.success {
+            if before.result.len() == after.result.len() {
+                diff_sequences(&mut vec![], &before.result, &after.result, &mut fields);
+            } else {
+                fields.push(FieldDiff {
+                    path: vec![],
+                    before: DynSolValue::Tuple(before.result.clone()),
+                    after: DynSolValue::Tuple(after.result.clone()),
+                });
+            }
+        }
+        let decode_errors = (
+            before.decode_error.as_ref().map(ToString::to_string),
+            after.decode_error.as_ref().map(ToString::to_string),
+        );
+
+        Self {
+            index,
+            success: (before.success != after.success).then_some((before.success, after.success)),
+            revert: (before.revert != after.revert).then(|| (before.revert.clone(), after.revert.clone())),
+            output: (before.output != after.output).then(|| (before.output.clone(), after.output.clone())),
+            decode_error: (decode_errors.0 != decode_errors.1).then_some(decode_errors),
+            fields,
+            gas_used: (before.gas_used != after.gas_used).then_some((before.gas_used, after.gas_used)),
+        }
+    }
+
+    /// Whether nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.success.is_none()
+            && self.revert.is_none()
+            && self.output.is_none()
+            && self.decode_error.is_none()
+            && self.fields.is_empty()
+            && self.gas_used.is_none()
+    }
+}
+
+/// Collects the changed values of two sequences of the same length into `diffs`
+fn diff_sequences(path: &mut Vec<usize>, before: &[DynSolValue], after: &[DynSolValue], diffs: &mut Vec<FieldDiff>) {
+    for (index, (before, after)) in before.iter().zip(after).enumerate() {
+        path.push(index);
+        diff_values(path, before, after, diffs);
+        path.pop();
+    }
+}
+
+/// Collects the changed values of `before` and `after` into `diffs`, descending into tuples
+/// and arrays of the same length
+fn diff_values(path: &mut Vec<usize>, before: &DynSolValue, after: &DynSolValue, diffs: &mut Vec<FieldDiff>) {
+    if before == after {
+        return;
+    }
+
+    match (children(before), children(after)) {
+        (Some(before), Some(after)) if before.len() == after.len() => diff_sequences(path, before, after, diffs),
+        _ => diffs.push(FieldDiff { path: path.clone(), before: before.clone(), after: after.clone() }),
+    }
+}
+
+/// Members of a tuple or elements of an array
+fn children(value: &DynSolValue) -> Option<&[DynSolValue]> {
+    value.as_fixed_seq().or_else(|| value.as_array())
+}
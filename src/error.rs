@@ -0,0 +1,120 @@
+
+use std::{fmt, time::Duration};
+
+use alloy::{contract, dyn_abi, eips::BlockId, primitives::{Address, Bytes}, transports::RpcError};
+
+use crate::Backend;
+
+
+/// Errors that can occur while executing a lens batch
+///
+/// Matches need a wildcard arm: `Evm` only exists with the `revm` feature, which any crate
+/// of the dependency graph can enable, and new variants may be added
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LensError {
+    /// The `eth_call` to the proxy failed (connection, RPC error, ...)
+    Transport(contract::Error),
+    /// An attempt took longer than the timeout of the retry policy
+    Timeout(Duration),
+    /// The embedded EVM failed to execute the batch
+    #[cfg(feature = "revm")]
+    Evm(String),
+    /// A dynamic call signature is invalid or its arguments don't match the function inputs
+    Encode(dyn_abi::Error),
+    /// The ABI has no function with this name accepting the given arguments
+    FunctionNotFound(String),
+    /// An override, call target or sender uses the proxy address
+    ProxyCollision(Address),
+    /// An impersonated sender also has ephemeral code, which the forwarder would replace
+    EphemeralSender(Address),
+    /// A call targets the address another call is sent from, which runs the forwarder instead of its code
+    SenderCalled(Address),
+    /// The selected block doesn't exist on the node
+    BlockNotFound(BlockId),
+    /// The batch uses a feature the selected backend can't execute
+    Unsupported {
+        /// Selected backend
+        backend: Backend,
+        /// Feature used by the batch
+        feature: &'static str,
+    },
+    /// The proxy or Multicall3 returned data that is not a valid batch output,
+    /// e.g. empty data from a node ignoring the state overrides
+    MalformedOutput(Bytes),
+    /// The proxy returned data for a call that is not a valid result envelope
+    MalformedEnvelope {
+        /// Index of the call in the batch
+        index: usize,
+        /// Raw data returned by the proxy for this call
+        data: Bytes,
+    },
+    /// The proxy returned a different number of results than calls sent
+    ResultCountMismatch {
+        /// Number of calls sent to the proxy
+        expected: usize,
+        /// Number of results returned by the proxy
+        actual: usize,
+    },
+}
+
+impl fmt::Display for LensError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(err) => write!(f, "lens call failed: {err}"),
+            Self::Timeout(timeout) => write!(f, "lens call timed out after {timeout:?}"),
+            #[cfg(feature = "revm")]
+            Self::Evm(err) => write!(f, "local execution failed: {err}"),
+            Self::Encode(err) => write!(f, "failed to encode call arguments: {err}"),
+            Self::FunctionNotFound(name) => write!(f, "no function {name} accepting the given arguments"),
+            Self::ProxyCollision(proxy) => {
+                write!(f, "proxy address {proxy} is also overridden, called or used as sender")
+            }
+            Self::EphemeralSender(sender) => {
+                write!(f, "sender {sender} can't be impersonated, it has ephemeral code")
+            }
+            Self::SenderCalled(sender) => {
+                write!(f, "sender {sender} can't be called, its code is replaced by the forwarder")
+            }
+            Self::BlockNotFound(block) => write!(f, "block {block} not found"),
+            Self::Unsupported { backend, feature } => {
+                write!(f, "{feature} not supported by the {backend:?} backend")
+            }
+            Self::MalformedOutput(data) => write!(f, "malformed batch output: {data}"),
+            Self::MalformedEnvelope { index, data } => {
+                write!(f, "malformed proxy envelope for call {index}: {data}")
+            }
+            Self::ResultCountMismatch { expected, actual } => {
+                write!(f, "proxy returned {actual} results for {expected} calls")
+            }
+        }
+    }
+}
+
+impl LensError {
+    /// Whether the request may succeed if sent again to the same provider: transport failures
+    /// other than a response of the node, e.g. a dropped connection, and timeouts
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(contract::Error::TransportError(RpcError::ErrorResp(_))) => false,
+            Self::Transport(_) | Self::Timeout(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for LensError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Encode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<contract::Error> for LensError {
+    fn from(err: contract::Error) -> Self {
+        Self::Transport(err)
+    }
+}
@@ -0,0 +1,24 @@
+use alloy::primitives::U256;
+
+/// Base cost of a transaction
+const TRANSACTION_GAS: u64 = 21_000;
+/// Cost of a zero calldata byte
+const ZERO_BYTE_GAS: u64 = 4;
+/// Cost of a non-zero calldata byte
+const NON_ZERO_BYTE_GAS: u64 = 16;
+/// Minimum cost per calldata token, EIP-7623
+const FLOOR_TOKEN_GAS: u64 = 10;
+
+/// Gas of a transaction sending `calldata` to an existing contract whose execution used `execution` gas
+///
+/// Applies the EIP-7623 calldata floor, access lists and contract creation are not supported
+pub(crate) fn transaction_gas(calldata: &[u8], execution: U256) -> U256 {
+    let zeros = calldata.iter().filter(|elt| **elt == 0).count() as u64;
+    let non_zeros = calldata.len() as u64 - zeros;
+
+    let standard = TRANSACTION_GAS + zeros * ZERO_BYTE_GAS + non_zeros * NON_ZERO_BYTE_GAS;
+    // non-zero bytes count as 4 tokens
+    let floor = TRANSACTION_GAS + (zeros + non_zeros * 4) * FLOOR_TOKEN_GAS;
+
+    execution.saturating_add(U256::from(standard)).max(U256::from(floor))
+}
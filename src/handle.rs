@@ -0,0 +1,86 @@
+
+use std::{fmt, marker::PhantomData};
+
+use alloy::sol_types::{self, SolCall};
+
+use crate::{CallResult, RevertReason};
+
+
+/// Typed reference to a call registered with [`Lens::add_call`](crate::Lens::add_call)
+///
+/// The handle remembers the position of the call in the batch so its result
+/// can be decoded back to `T::Return`
+pub struct Handle<T> {
+    /// Index of the call in the batch
+    index: usize,
+    _call: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(super) fn new(index: usize) -> Self {
+        Self { index, _call: PhantomData }
+    }
+
+    /// Position of the call in the batch results
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T: SolCall> Handle<T> {
+    /// Decodes the result of this call from the results of [`Lens::call`](crate::Lens::call)
+    pub fn get(&self, results: &[CallResult]) -> Result<T::Return, CallFailure> {
+        let result = results.get(self.index).ok_or(CallFailure::Missing(self.index))?;
+
+        if !result.success {
+            let revert = result.revert.clone().unwrap_or_else(|| RevertReason::Raw(result.output.clone()));
+            return Err(CallFailure::Reverted(revert));
+        }
+
+        result.decode_as::<T>().map_err(CallFailure::Decode)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle").field("index", &self.index).finish()
+    }
+}
+
+/// Reason why a typed call result is not available
+#[derive(Debug)]
+pub enum CallFailure {
+    /// No result at the handle index, the handle belongs to another batch
+    Missing(usize),
+    /// The call reverted
+    Reverted(RevertReason),
+    /// The call succeeded but its output doesn't match `T::Return`
+    Decode(sol_types::Error),
+}
+
+impl fmt::Display for CallFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(index) => write!(f, "no result for call {index}"),
+            Self::Reverted(revert) => write!(f, "call reverted ({revert})"),
+            Self::Decode(err) => write!(f, "failed to decode call output: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CallFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
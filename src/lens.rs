@@ -1,12 +1,25 @@
 
 use alloy::{
-    dyn_abi::{FunctionExt, SolType},
-    network::Network, primitives::{Address, Bytes},
-    providers::Provider, rpc::types::state::{AccountOverride, StateOverride},
+    consensus::BlockHeader,
+    dyn_abi::{DynSolValue, JsonAbiExt as _, SolType},
+    eips::BlockId,
+    json_abi::{Error, Function, JsonAbi},
+    network::{primitives::HeaderResponse, BlockResponse, Network}, primitives::{Address, Bytes, B256, U256},
+    providers::Provider, rpc::types::{state::{AccountOverride, StateOverride}, BlockOverrides},
     sol_types::{JsonAbiExt, SolCall}
 };
 
-use crate::{call::Call, contract::IProxy::{self, IProxyInstance}, CallResult};
+use std::{future::Future, iter, ops::Range, sync::Arc};
+
+use futures::{future, stream, FutureExt, Stream, StreamExt, TryStreamExt};
+
+use crate::{cache::{CacheKey, LensCache}, call::Call, call_result::decode_envelope, probe::probe, gas::transaction_gas, signature::{resolve_args, SignatureArg}, contract::{forwarder_bytecode, IMulticall3, IProxy::{self, IProxyInstance}, MULTICALL3_ADDRESS, PROXY_BYTECODE}, Backend, BatchLimits, BlockSeries, Capabilities, CallDiff, CallResult, ExecutionMode, GasMeasurement, Handle, LensError, LensPlan, RetryPolicy};
+
+/// Address the proxy is installed at by [`Lens::new`]
+const DEFAULT_PROXY_ADDRESS: Address = Address::repeat_byte(0x01);
+
+/// Item of [`Lens::watch`] streams, a header and the results of the batch executed at it
+type BlockUpdate<N> = Result<(<N as Network>::HeaderResponse, Vec<CallResult>), LensError>;
 
 /// A struct that acts as a lens to interact with a smart contract proxy
 pub struct Lens<P, N>
@@ -16,10 +29,30 @@ where
 {
     /// Proxy contract instance
     proxy: IProxyInstance<P, N>,
+    /// Proxy contract instances on the fallback providers, tried in order when the primary one fails
+    fallbacks: Vec<IProxyInstance<P, N>>,
+    /// Timeout and retries of each request
+    retry_policy: RetryPolicy,
+    /// Store of the outputs of `eth_call`s pinned to a block hash
+    cache: Option<Arc<dyn LensCache>>,
     /// Collection of contract calls
     calls: Vec<Call>,
     /// State overrides for ephemeral execution
     state_overrides: StateOverride,
+    /// Block the batch is executed at, the node's latest block if unset
+    block: Option<BlockId>,
+    /// Header fields overridden for execution
+    block_overrides: Option<BlockOverrides>,
+    /// Limits used to split the batch into several `eth_call`s
+    limits: BatchLimits,
+    /// How the proxy executes the calls
+    mode: ExecutionMode,
+    /// How the gas of each call is measured
+    gas_measurement: GasMeasurement,
+    /// Contract the batch is executed through
+    backend: Backend,
+    /// Custom errors used to decode reverts
+    errors: Vec<Error>,
 }
 
 impl<P, N> Lens<P, N>
@@ -40,29 +73,364 @@ where
     /// # })
     /// ```
     pub fn new(provider: P) -> Self {
-        let proxy_address = Address::repeat_byte(0x01);
-        let mut state_override = StateOverride::default();
-        state_override.insert(
-            proxy_address,
-            AccountOverride::default().with_code(IProxy::DEPLOYED_BYTECODE.clone())
-        );
+        Self::new_with_proxy(provider, DEFAULT_PROXY_ADDRESS)
+    }
 
+    /// Constructs a new `Lens` instance with the proxy contract installed at `proxy`
+    ///
+    /// The default proxy address `0x0101…01` may hold a contract on some chains, the proxy
+    /// code would then shadow it. Ephemeral overrides, call targets and senders must not
+    /// use the proxy address, [`Lens::call`] fails with [`LensError::ProxyCollision`] otherwise.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::Address, providers::ProviderBuilder};
+    /// # tokio_test::block_on(async {
+    /// let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let lens = Lens::new_with_proxy(&provider, Address::repeat_byte(0xfe));
+    /// # })
+    /// ```
+    pub fn new_with_proxy(provider: P, proxy: Address) -> Self {
         Self {
-            proxy: IProxyInstance::new(proxy_address, provider),
+            proxy: IProxyInstance::new(proxy, provider),
+            fallbacks: vec![],
+            retry_policy: RetryPolicy::default(),
+            cache: None,
             calls: vec![],
-            state_overrides: state_override,
+            state_overrides: StateOverride::default(),
+            block: None,
+            block_overrides: None,
+            limits: BatchLimits::default(),
+            mode: ExecutionMode::default(),
+            gas_measurement: GasMeasurement::default(),
+            backend: Backend::default(),
+            errors: vec![],
+        }
+    }
+
+    /// Constructs a `Lens` executing the batch described by `plan` against `provider`
+    ///
+    /// Calls are decoded with the ABI fragments stored in the plan, typed [`Handle`]s
+    /// obtained from the original lens keep working on the results.
+    pub fn from_plan(provider: P, plan: LensPlan) -> Self {
+        Self {
+            proxy: IProxyInstance::new(plan.proxy, provider),
+            fallbacks: vec![],
+            retry_policy: RetryPolicy::default(),
+            cache: None,
+            calls: plan.calls,
+            state_overrides: plan.state_overrides,
+            block: plan.block,
+            block_overrides: plan.block_overrides,
+            limits: plan.limits,
+            mode: plan.mode,
+            gas_measurement: plan.gas_measurement,
+            backend: plan.backend,
+            errors: plan.errors,
+        }
+    }
+
+    /// Exports the batch as a serializable [`LensPlan`], to be reloaded with [`Lens::from_plan`]
+    pub fn plan(&self) -> LensPlan {
+        LensPlan {
+            proxy: *self.proxy.address(),
+            calls: self.calls.clone(),
+            state_overrides: self.state_overrides.clone(),
+            block: self.block,
+            block_overrides: self.block_overrides.clone(),
+            limits: self.limits,
+            mode: self.mode,
+            gas_measurement: self.gas_measurement,
+            backend: self.backend,
+            errors: self.errors.clone(),
         }
     }
 
     /// Adds an ephemeral contract to the state override for execution
     /// 
-    /// This could be for an ephemeral lens contract or an interacted contract
+    /// This could be for an ephemeral lens contract or an interacted contract,
+    /// other overrides set for `address` are kept
     /// 
     pub fn with_ephemeral(&mut self, address: &Address, run_bytecode: Bytes) -> &mut Self {
-        self.state_overrides.insert(
-            *address,
-            AccountOverride::default().with_code(run_bytecode)
-        );
+        self.account_override(address).code = Some(run_bytecode);
+
+        self
+    }
+
+    /// Overrides the ether balance of `address` for execution
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{address, U256}, providers::ProviderBuilder};
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_balance(&address!("0x000000000000000000000000000000000000dEaD"), U256::from(10).pow(U256::from(18)));
+    /// # })
+    /// ```
+    pub fn with_balance(&mut self, address: &Address, balance: U256) -> &mut Self {
+        self.account_override(address).balance = Some(balance);
+
+        self
+    }
+
+    /// Overrides the nonce of `address` for execution
+    pub fn with_nonce(&mut self, address: &Address, nonce: u64) -> &mut Self {
+        self.account_override(address).nonce = Some(nonce);
+
+        self
+    }
+
+    /// Replaces the whole storage of `address` with `slots` for execution
+    ///
+    /// Slots missing from the override read as zero. Slots previously set
+    /// through [`Lens::with_state_diff`] are kept as part of the new storage.
+    pub fn with_state(&mut self, address: &Address, slots: impl IntoIterator<Item = (B256, B256)>) -> &mut Self {
+        let account = self.account_override(address);
+        let mut state = account.state.take().unwrap_or_default();
+        state.extend(account.state_diff.take().unwrap_or_default());
+        state.extend(slots);
+        account.state = Some(state);
+
+        self
+    }
+
+    /// Overrides individual storage slots of `address` for execution, other slots keep their value
+    ///
+    /// If the whole storage was replaced through [`Lens::with_state`], the slots are added to it.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{address, B256}, providers::ProviderBuilder};
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_state_diff(
+    ///     &address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+    ///     [(B256::ZERO, B256::with_last_byte(1))]
+    /// );
+    /// # })
+    /// ```
+    pub fn with_state_diff(&mut self, address: &Address, slots: impl IntoIterator<Item = (B256, B256)>) -> &mut Self {
+        let account = self.account_override(address);
+        match account.state.as_mut() {
+            Some(state) => state.extend(slots),
+            None => account.state_diff.get_or_insert_with(Default::default).extend(slots),
+        }
+
+        self
+    }
+
+    /// Override entry of `address`, created if missing
+    fn account_override(&mut self, address: &Address) -> &mut AccountOverride {
+        self.state_overrides.entry(*address).or_default()
+    }
+
+    /// Overrides header fields of the executed block, e.g. its timestamp or number
+    ///
+    /// State is still read at the selected block, [`CallResult::block_number`] reports
+    /// that block and not the overridden number.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{providers::ProviderBuilder, rpc::types::BlockOverrides};
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// // Evaluate as if a year has passed
+    /// lens.with_block_overrides(BlockOverrides::default().with_time(1_800_000_000));
+    /// # })
+    /// ```
+    pub fn with_block_overrides(&mut self, overrides: BlockOverrides) -> &mut Self {
+        self.block_overrides = Some(overrides);
+
+        self
+    }
+
+    /// Registers custom errors used to decode reverted calls into [`RevertReason::Custom`](crate::RevertReason::Custom)
+    pub fn with_errors(&mut self, errors: impl IntoIterator<Item = Error>) -> &mut Self {
+        self.errors.extend(errors);
+
+        self
+    }
+
+    /// Registers the custom errors of `abi`, e.g. generated by `sol!` with `#[sol(abi)]`
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{providers::ProviderBuilder, sol};
+    /// sol! {
+    ///     #[sol(abi)]
+    ///     interface IPool {
+    ///         error InsufficientLiquidity(uint256 available);
+    ///     }
+    /// }
+    ///
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_error_abi(&IPool::abi::contract());
+    /// # })
+    /// ```
+    pub fn with_error_abi(&mut self, abi: &JsonAbi) -> &mut Self {
+        self.with_errors(abi.errors().cloned())
+    }
+
+    /// Selects the block the batch is executed at
+    ///
+    /// Tags and numbers are resolved to a block hash before execution so all calls read
+    /// the same state, the resolved number is reported in [`CallResult::block_number`].
+    /// `pending` is sent as is.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{eips::BlockId, providers::ProviderBuilder};
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_block(BlockId::finalized());
+    /// # })
+    /// ```
+    pub fn with_block(&mut self, block: impl Into<BlockId>) -> &mut Self {
+        self.block = Some(block.into());
+
+        self
+    }
+
+    /// Sets the limits used to split the batch into several `eth_call`s
+    ///
+    /// Chunks are executed with bounded concurrency, all of them at the same block and with
+    /// the same state overrides, and their results are returned in the registration order.
+    /// Without a selected block, the latest one is resolved to its hash before the chunks are sent.
+    /// Limits are ignored in [`ExecutionMode::Sequential`] as chunks wouldn't share state.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{BatchLimits, Lens};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_limits(BatchLimits::default().with_max_calls(200).with_concurrency(4));
+    /// # })
+    /// ```
+    pub fn with_limits(&mut self, limits: BatchLimits) -> &mut Self {
+        self.limits = limits;
+
+        self
+    }
+
+    /// Sets how the proxy executes the calls, [`ExecutionMode::Isolated`] by default
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{ExecutionMode, Lens};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// // approve, then swap, then check the balance
+    /// lens.with_mode(ExecutionMode::Sequential);
+    /// # })
+    /// ```
+    pub fn with_mode(&mut self, mode: ExecutionMode) -> &mut Self {
+        self.mode = mode;
+
+        self
+    }
+
+    /// Sets how the gas of each call is measured, [`GasMeasurement::Batch`] by default
+    ///
+    /// Cold and warm gas are reported in [`CallResult::cold_gas_used`] and [`CallResult::warm_gas_used`],
+    /// so calls of a batch can be compared regardless of their order. They are left unset for
+    /// sequential calls whose measured run, on the initial state, differs from their actual run.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{ExecutionMode, GasMeasurement, Lens};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_mode(ExecutionMode::Sequential)
+    ///     .with_gas_measurement(GasMeasurement::ColdAndWarm);
+    /// # })
+    /// ```
+    pub fn with_gas_measurement(&mut self, gas_measurement: GasMeasurement) -> &mut Self {
+        self.gas_measurement = gas_measurement;
+
+        self
+    }
+
+    /// Adds a provider the batch is executed on when the previous ones fail, reject the request
+    /// or return a malformed output, e.g. ignoring the state overrides
+    ///
+    /// Fallbacks are tried in the order they were added, [`CallResult::provider`] reports
+    /// which provider produced a result.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{Lens, RetryPolicy};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # use std::time::Duration;
+    /// # tokio_test::block_on(async {
+    /// let primary = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let fallback = ProviderBuilder::new().connect("http://localhost:8081").await.unwrap();
+    ///
+    /// let mut lens = Lens::new(primary);
+    /// lens.with_fallback(fallback)
+    ///     .with_retry_policy(RetryPolicy::default().with_timeout(Duration::from_secs(5)).with_retries(2));
+    /// # })
+    /// ```
+    pub fn with_fallback(&mut self, provider: P) -> &mut Self {
+        self.fallbacks.push(IProxyInstance::new(*self.proxy.address(), provider));
+
+        self
+    }
+
+    /// Sets the timeout and retries applied to each request, a single attempt per provider by default
+    pub fn with_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+
+        self
+    }
+
+    /// Sets the store caching the outputs of historical `eth_call`s, e.g. a shared [`LruCache`](crate::LruCache)
+    ///
+    /// Outputs are keyed on the block hash, the calldata and the overrides. Only `eth_call`s pinned
+    /// to a block hash are cached: a block selected with [`Lens::with_block`] other than `pending`,
+    /// [`Lens::diff_blocks`], [`Lens::call_series`] and [`Lens::watch`] runs, or a split batch
+    /// at the latest block.
+    /// A single `eth_call` at `latest` and `pending` queries always reach the node.
+    pub fn with_cache(&mut self, cache: Arc<dyn LensCache>) -> &mut Self {
+        self.cache = Some(cache);
+
+        self
+    }
+
+    /// Sets the contract [`Lens::call`] executes the batch through, [`Backend::Proxy`] by default
+    ///
+    /// [`Backend::Multicall3`] serves plain reads on nodes rejecting state overrides,
+    /// [`Lens::call`] fails with [`LensError::Unsupported`] if the batch needs the proxy.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{Backend, Lens};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_backend(Backend::Multicall3);
+    /// # })
+    /// ```
+    pub fn with_backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
 
         self
     }
@@ -96,32 +464,960 @@ where
     /// ```
     pub fn with_call<T>(&mut self, address: &Address, args: <T::Parameters<'_> as SolType>::RustType) -> &mut Self
     where 
-        T: SolCall + JsonAbiExt,
-        T::Abi: FunctionExt
+        T: SolCall + JsonAbiExt<Abi = Function>
+    {
+        self.add_call::<T>(address, args);
+
+        self
+    }
+
+    /// Registers a contract call like [`with_call`](Self::with_call) and returns a typed
+    /// [`Handle`] to decode its result as `T::Return`
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::address, providers::ProviderBuilder, sol};
+    /// #
+    /// sol! {
+    ///     interface IERC20 {
+    ///         #[sol(abi)]
+    ///         function decimals() external view returns (uint8);
+    ///     }
+    /// }
+    /// #
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let weth = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// let decimals = lens.add_call::<IERC20::decimalsCall>(&weth, ());
+    ///
+    /// let results = lens.call().await?;
+    /// let decimals: u8 = decimals.get(&results)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_call<T>(&mut self, address: &Address, args: <T::Parameters<'_> as SolType>::RustType) -> Handle<T>
+    where 
+        T: SolCall + JsonAbiExt<Abi = Function>
     {
         let call = T::new(args);
-        self.calls.push(
-            Call::new(
-                |data| T::abi().abi_decode_output(data).unwrap(),
-                *address,
-                call.abi_encode().into()
-            )
-        );
+        self.calls.push(Call::new(T::abi(), *address, call.abi_encode().into()));
+
+        Handle::new(self.calls.len() - 1)
+    }
+
+    /// Registers a call to `function` of the contract at `address` with runtime `args`
+    ///
+    /// The dynamic counterpart of [`Lens::with_call`], for ABIs loaded at runtime
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{dyn_abi::DynSolValue, json_abi::Function, primitives::address, providers::ProviderBuilder};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let weth = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    /// let balance_of = Function::parse("balanceOf(address) returns (uint256)")?;
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_dyn_call(&weth, &balance_of, &[DynSolValue::Address(weth)])?;
+    ///
+    /// let results = lens.call().await?;
+    /// let balance = results[0].result[0].as_uint();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_dyn_call(&mut self, address: &Address, function: &Function, args: &[DynSolValue]) -> Result<&mut Self, LensError> {
+        self.add_dyn_call(address, function, args)?;
+
+        Ok(self)
+    }
+
+    /// Registers a call to `function` of the contract at `address` with runtime `args`,
+    /// returning its index in the batch results
+    ///
+    /// Fails with [`LensError::Encode`] if `args` don't match the function inputs
+    pub fn add_dyn_call(&mut self, address: &Address, function: &Function, args: &[DynSolValue]) -> Result<usize, LensError> {
+        let argument = function.abi_encode_input(args).map_err(LensError::Encode)?;
+        self.calls.push(Call::new(function.clone(), *address, argument.into()));
+
+        Ok(self.calls.len() - 1)
+    }
+
+    /// Registers a call from a human-readable `signature` with string or [`DynSolValue`] `args`
+    ///
+    /// The signature drives both encoding and decoding, outputs follow the inputs as in
+    /// `"balanceOf(address)(uint256)"` or `"balanceOf(address) returns (uint256)"`.
+    /// String arguments are parsed against their input type.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::providers::ProviderBuilder;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let weth = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse()?;
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_signature_call(&weth, "balanceOf(address)(uint256)", ["0x000000000000000000000000000000000000dEaD"])?
+    ///     .with_signature_call(&weth, "totalSupply()(uint256)", Vec::<&str>::new())?;
+    ///
+    /// let results = lens.call().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_signature_call<A>(&mut self, address: &Address, signature: &str, args: impl IntoIterator<Item = A>) -> Result<&mut Self, LensError>
+    where
+        A: Into<SignatureArg>
+    {
+        self.add_signature_call(address, signature, args)?;
+
+        Ok(self)
+    }
+
+    /// Registers a call like [`with_signature_call`](Self::with_signature_call),
+    /// returning its index in the batch results
+    pub fn add_signature_call<A>(&mut self, address: &Address, signature: &str, args: impl IntoIterator<Item = A>) -> Result<usize, LensError>
+    where
+        A: Into<SignatureArg>
+    {
+        let function = Function::parse(signature).map_err(|err| LensError::Encode(err.into()))?;
+        let args = resolve_args(&function, args.into_iter().map(Into::into).collect())
+            .map_err(LensError::Encode)?;
+
+        self.add_dyn_call(address, &function, &args)
+    }
+
+    /// Registers a call to the function `name` of `abi` on the contract at `address`
+    ///
+    /// Among overloads, the first function accepting `args` is called.
+    /// Fails with [`LensError::FunctionNotFound`] if no function `name` accepts `args`
+    pub fn with_abi_call(&mut self, address: &Address, abi: &JsonAbi, name: &str, args: &[DynSolValue]) -> Result<&mut Self, LensError> {
+        let function = abi.function(name)
+            .into_iter()
+            .flatten()
+            .find(|elt| elt.abi_encode_input(args).is_ok())
+            .ok_or_else(|| LensError::FunctionNotFound(name.to_string()))?;
+
+        self.with_dyn_call(address, function, args)
+    }
+
+    /// Sets the ether value sent with the last registered call
+    ///
+    /// The proxy balance is overridden to fund the value of all calls
+    ///
+    /// # Panics
+    /// Panics if no call was registered
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{address, utils::parse_ether}, providers::ProviderBuilder, sol};
+    /// #
+    /// sol! {
+    ///     interface IWETH {
+    ///         #[sol(abi)]
+    ///         function deposit() external payable;
+    ///     }
+    /// }
+    /// #
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let weth = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_call::<IWETH::depositCall>(&weth, ())
+    ///     .with_value(parse_ether("1").unwrap())
+    ///     .with_gas(100_000);
+    /// # })
+    /// ```
+    pub fn with_value(&mut self, value: U256) -> &mut Self {
+        self.last_call().with_value(value);
+
+        self
+    }
+
+    /// Sets the gas limit of the last registered call
+    ///
+    /// A call running out of gas is reported as failed without starving the rest of the batch
+    ///
+    /// # Panics
+    /// Panics if no call was registered
+    pub fn with_gas(&mut self, gas: u64) -> &mut Self {
+        self.last_call().with_gas(U256::from(gas));
 
         self
     }
 
+    /// Sets the address the last registered call is sent from
+    ///
+    /// A forwarder is installed at `sender` so the callee sees it as `msg.sender`.
+    /// `sender` has contract code for the whole batch, replacing its own, which every call sees:
+    /// - code size and code hash checks of `sender`, e.g. `isContract`, see a contract
+    /// - calls to `sender` from other callers than the proxy succeed with empty return data,
+    ///   so ERC721 and ERC1155 safe transfers to `sender` revert on its `onERC*Received` hook
+    /// - the own code of a contract `sender` doesn't run
+    ///
+    /// `sender` can't also be an ephemeral contract nor the target of another call.
+    ///
+    /// # Panics
+    /// Panics if no call was registered
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{address, U256}, providers::ProviderBuilder, sol};
+    /// #
+    /// sol! {
+    ///     interface IERC20 {
+    ///         #[sol(abi)]
+    ///         function transfer(address to, uint256 amount) external returns (bool);
+    ///     }
+    /// }
+    /// #
+    /// # tokio_test::block_on(async {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await.unwrap();
+    /// let weth = address!("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    /// let holder = address!("0x2F0b23f53734252Bda2277357e97e1517d6B042A");
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// // Simulate a transfer from `holder`
+    /// lens.with_call::<IERC20::transferCall>(&weth, (weth, U256::from(1)))
+    ///     .with_sender(holder);
+    /// # })
+    /// ```
+    pub fn with_sender(&mut self, sender: Address) -> &mut Self {
+        self.last_call().with_sender(sender);
+
+        self
+    }
+
+    fn last_call(&mut self) -> &mut Call {
+        self.calls.last_mut().expect("no call registered")
+    }
+
+    /// Probes the primary node once for the features the lens relies on
+    ///
+    /// Checks state and block overrides, `PUSH0` support and the availability of the state
+    /// `depth` blocks behind the head, e.g. to pick a [`Backend`] at startup.
+    /// Requests rejected by the node are reported as unsupported, transport failures as errors.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::providers::ProviderBuilder;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let mut lens = Lens::new(&provider);
+    /// let capabilities = lens.probe(10_000).await?;
+    /// lens.with_backend(capabilities.backend());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn probe(&self, depth: u64) -> Result<Capabilities, LensError> {
+        probe(self.proxy.provider(), depth).await
+    }
+
     /// Executes all registered calls and collects their results
-    pub async fn call(&self) -> Vec<CallResult> {
-        let calls = self.calls.iter()
-            .map(|elt| elt.encode())
+    ///
+    /// Fails if the `eth_call` itself fails or if the proxy output can't be decoded,
+    /// a reverting call is reported through its [`CallResult`] instead
+    pub async fn call(&self) -> Result<Vec<CallResult>, LensError> {
+        self.validate_backend()?;
+
+        self.call_at(self.block).await
+    }
+
+    /// Executes the batch at two blocks and returns the calls whose results changed
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{eips::BlockId, providers::ProviderBuilder};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// # let lens = Lens::new(&provider);
+    /// // What changed over the last event, mined at block 19_000_000
+    /// let diffs = lens.diff_blocks(BlockId::number(18_999_999), BlockId::number(19_000_000)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn diff_blocks(&self, before: impl Into<BlockId>, after: impl Into<BlockId>) -> Result<Vec<CallDiff>, LensError> {
+        self.validate_backend()?;
+
+        let (before, after) = future::try_join(
+            self.call_at(Some(before.into())),
+            self.call_at(Some(after.into()))
+        ).await?;
+
+        CallDiff::between(&before, &after)
+    }
+
+    /// Executes the batch and `other` and returns the calls whose results changed from `self` to `other`
+    ///
+    /// `other` is expected to hold the same calls, e.g. reloaded from [`Lens::plan`]
+    /// with a different override set. Both batches run at the block selected on `self`,
+    /// the latest one if unset, resolved once so they read the same state.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{Address, Bytes}, providers::ProviderBuilder};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// # let lens = Lens::new(&provider);
+    /// # let (pool, patched) = (Address::ZERO, Bytes::new());
+    /// // Same calls with a patched contract
+    /// let mut patched_lens = Lens::from_plan(&provider, lens.plan());
+    /// patched_lens.with_ephemeral(&pool, patched);
+    ///
+    /// let diffs = lens.diff_with(&patched_lens).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn diff_with<Q, M>(&self, other: &Lens<Q, M>) -> Result<Vec<CallDiff>, LensError>
+    where
+        M: Network,
+        Q: Provider<M>
+    {
+        self.validate_backend()?;
+        other.validate_backend()?;
+
+        let (block, block_number) = self.resolve_block(Some(self.block.unwrap_or(BlockId::latest()))).await?;
+        let block_number = self.block.and(block_number);
+        let (before, after) = future::try_join(
+            self.execute(block, block_number),
+            other.execute(block, block_number)
+        ).await?;
+
+        CallDiff::between(&before, &after)
+    }
+
+    /// Executes the batch at `block`, the node's latest block if unset
+    async fn call_at(&self, block: Option<BlockId>) -> Result<Vec<CallResult>, LensError> {
+        // Each `eth_call` sent at `latest` could read a different head
+        let pinned = block.or_else(|| self.is_split().then(BlockId::latest));
+        let (pinned, block_number) = self.resolve_block(pinned).await?;
+
+        self.execute(pinned, block.and(block_number)).await
+    }
+
+    /// Executes the batch at every block of `series` and returns the results in block order
+    ///
+    /// Blocks are executed concurrently, each of them as with [`Lens::call`] pinned to the hash of its number.
+    /// A block failing with a [retryable](LensError::is_retryable) error, once the retries of the [`RetryPolicy`] are exhausted,
+    /// is executed again up to [`BlockSeries::retries`] times after the delays of the policy,
+    /// a block still failing is reported with its error and doesn't fail the rest of the series.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::{BlockSeries, Lens};
+    /// # use alloy::providers::ProviderBuilder;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let lens = Lens::new(&provider);
+    /// let series = lens.call_series(&BlockSeries::new(19_000_000..=19_001_000, 100).with_retries(2)).await?;
+    ///
+    /// for (block, results) in series {
+    ///     match results {
+    ///         Ok(results) => println!("block {block}: {} results", results.len()),
+    ///         Err(err) => println!("block {block} failed: {err}"),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn call_series(&self, series: &BlockSeries) -> Result<Vec<(u64, Result<Vec<CallResult>, LensError>)>, LensError> {
+        self.validate_backend()?;
+
+        let results = stream::iter(series.blocks())
+            .map(|number| async move {
+                let mut attempt = 0;
+                loop {
+                    // Pinned to the block hash, so historical runs are served from the cache
+                    let results = match self.resolve_block(Some(BlockId::number(number))).await {
+                        Ok((block, _)) => self.execute(block, Some(number)).await,
+                        Err(err) => Err(err),
+                    };
+                    match results {
+                        Err(err) if err.is_retryable() && attempt < series.retries => {
+                            tokio::time::sleep(self.retry_policy.delay(attempt)).await;
+                            attempt += 1;
+                        }
+                        results => return (number, results),
+                    }
+                }
+            })
+            .buffered(series.concurrency.max(1))
+            .collect()
+            .await;
+
+        Ok(results)
+    }
+
+    /// Executes the batch on every new block, yielding each block header with the batch results
+    ///
+    /// Each run is pinned to the hash of its block, the block selected with [`Lens::with_block`]
+    /// is ignored. Blocks received while a run is in progress are coalesced, only the newest
+    /// one is executed next. A failed run is yielded as an error and doesn't end the stream.
+    /// Requires a provider supporting subscriptions, e.g. connected over WebSocket.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{consensus::BlockHeader, providers::{ProviderBuilder, WsConnect}};
+    /// # use futures::StreamExt;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect_ws(WsConnect::new("ws://localhost:8546")).await?;
+    /// let lens = Lens::new(&provider);
+    /// let mut blocks = Box::pin(lens.watch().await?);
+    ///
+    /// while let Some(update) = blocks.next().await {
+    ///     let (header, results) = update?;
+    ///     println!("block {}: {} results", header.number(), results.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn watch(&self) -> Result<impl Stream<Item = BlockUpdate<N>> + '_, LensError> {
+        self.validate_backend()?;
+
+        let headers = self.proxy.provider()
+            .subscribe_blocks()
+            .await
+            .map_err(|err| LensError::Transport(err.into()))?
+            .into_stream();
+
+        self.watch_headers(headers)
+    }
+
+    /// Executes the batch on every header of `headers`, as [`Lens::watch`] does with new blocks
+    ///
+    /// Headers ready when a run ends are coalesced, only the newest one is executed next.
+    /// Useful with headers from another source than a subscription of the provider, e.g. polling.
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{network::Ethereum, providers::{Provider, ProviderBuilder}, rpc::types::Header};
+    /// # use futures::{Stream, StreamExt};
+    /// # async fn run(headers: impl Stream<Item = Header>) -> Result<(), Box<dyn std::error::Error>> {
+    /// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+    /// let lens = Lens::new(&provider);
+    /// let mut blocks = Box::pin(lens.watch_headers(headers)?);
+    ///
+    /// while let Some(update) = blocks.next().await {
+    ///     let (header, results) = update?;
+    ///     println!("block {}: {} results", header.number, results.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_headers<'a, S>(&'a self, headers: S) -> Result<impl Stream<Item = BlockUpdate<N>> + 'a, LensError>
+    where
+        S: Stream<Item = N::HeaderResponse> + 'a
+    {
+        self.validate_backend()?;
+
+        Ok(stream::unfold(Box::pin(headers.fuse()), move |mut headers| async move {
+            let mut header = headers.next().await?;
+            // Skip the headers superseded while the previous run was in progress
+            while let Some(Some(newer)) = headers.next().now_or_never() {
+                header = newer;
+            }
+
+            let results = self.execute(BlockId::hash(header.hash()), Some(header.number())).await;
+            Some((results.map(|results| (header, results)), headers))
+        }))
+    }
+
+    /// Executes the batch at `block`, reporting `block_number` in the results
+    async fn execute(&self, block: BlockId, block_number: Option<u64>) -> Result<Vec<CallResult>, LensError> {
+        let chunks: Vec<Vec<CallResult>> = stream::iter(self.ranges())
+            .map(|range| self.execute_chunk(range, block))
+            .buffered(self.limits.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(
+            chunks.into_iter()
+                .flatten()
+                .map(|mut result| {
+                    result.block_number = block_number;
+                    result
+                })
+                .collect()
+        )
+    }
+
+    /// Executes the batch in an embedded revm instance instead of an `eth_call`
+    ///
+    /// State is read from `db`, e.g. an in-memory `CacheDB<EmptyDB>` for fully offline runs,
+    /// or a `CacheDB` over an `AlloyDB` fetching a node's state at a block, re-exported
+    /// through [`revm`](crate::revm). Overrides apply as with [`Lens::call`],
+    /// the selected block is ignored and [`CallResult::block_number`] is left unset.
+    /// The batch always runs through the proxy, whatever the selected [`Backend`].
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::Lens;
+    /// # use alloy::{primitives::{address, Address}, providers::ProviderBuilder, sol};
+    /// # use revm::database::{CacheDB, EmptyDB};
+    /// #
+    /// sol! {
+    ///     #[sol(deployed_bytecode="602a5f5260205ff3")]
+    ///     interface IAnswer {
+    ///         #[sol(abi)]
+    ///         function answer() external view returns (uint256);
+    ///     }
+    /// }
+    ///
+    /// // The provider is never used by local execution
+    /// let provider = ProviderBuilder::new().connect_http("http://localhost:8080".parse().unwrap());
+    /// let answer = Address::repeat_byte(0x42);
+    ///
+    /// let mut lens = Lens::new(&provider);
+    /// lens.with_ephemeral(&answer, IAnswer::DEPLOYED_BYTECODE.clone())
+    ///     .with_call::<IAnswer::answerCall>(&answer, ());
+    ///
+    /// let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    /// assert_eq!(results[0].result[0].as_uint().unwrap().0.to::<u64>(), 42);
+    /// ```
+    ///
+    /// Forking a node's state, which needs a multi-threaded tokio runtime
+    /// ```
+    /// # use alloy_ephemeral_lens::{CallResult, Lens, LensError};
+    /// # use alloy::{eips::BlockId, network::Ethereum, providers::RootProvider};
+    /// use alloy_ephemeral_lens::revm::database::{AlloyDB, CacheDB, WrapDatabaseAsync};
+    ///
+    /// fn call_forked(lens: &Lens<&RootProvider, Ethereum>, provider: RootProvider, block: BlockId)
+    ///     -> Result<Vec<CallResult>, LensError>
+    /// {
+    ///     let fork = WrapDatabaseAsync::new(AlloyDB::new(provider, block))
+    ///         .expect("multi-threaded tokio runtime");
+    ///
+    ///     lens.call_local(CacheDB::new(fork))
+    /// }
+    /// ```
+    #[cfg(feature = "revm")]
+    pub fn call_local<DB: revm::DatabaseRef>(&self, db: DB) -> Result<Vec<CallResult>, LensError> {
+        self.validate()?;
+
+        let mut results = Vec::with_capacity(self.calls.len());
+        for range in self.ranges() {
+            let calls = &self.calls[range.clone()];
+            let arguments = calls.iter().map(|elt| elt.encode()).collect();
+            let calldata = proxy_calldata(self.mode, arguments);
+
+            let output = crate::local::execute(
+                &db,
+                *self.proxy.address(),
+                calldata.clone(),
+                &self.chunk_overrides(calls, 1),
+                self.block_overrides.as_ref(),
+            )?;
+            let mut chunk = self.decode_chunk(range.clone(), &calldata, &output)?;
+
+            if let Some((arguments, runs)) = self.measurement_pass(calls) {
+                let output = crate::local::execute(
+                    &db,
+                    *self.proxy.address(),
+                    proxy_calldata(ExecutionMode::Isolated, arguments),
+                    &self.chunk_overrides(calls, runs),
+                    self.block_overrides.as_ref(),
+                )?;
+                self.apply_measurement(range, &mut chunk, &output)?;
+            }
+            results.extend(chunk);
+        }
+
+        Ok(results)
+    }
+
+    /// Ranges of the calls sent in a single `eth_call`
+    fn ranges(&self) -> Vec<Range<usize>> {
+        match self.mode {
+            ExecutionMode::Isolated => self.limits.chunks(&self.calls),
+            ExecutionMode::Sequential => BatchLimits::default().chunks(&self.calls),
+        }
+    }
+
+    /// Whether the batch is sent in several `eth_call`s, either split into chunks or measured separately
+    fn is_split(&self) -> bool {
+        self.ranges().len() > 1 || self.is_measured()
+    }
+
+    /// Executes the calls in `range` following the retry policy, recording the provider and attempts
+    async fn execute_chunk(&self, range: Range<usize>, block: BlockId) -> Result<Vec<CallResult>, LensError> {
+        let (mut results, provider, attempts) = self.with_providers(|proxy| self.try_chunk(proxy, range.clone(), block)).await?;
+        for result in &mut results {
+            result.provider = provider;
+            result.attempts = attempts;
+        }
+
+        Ok(results)
+    }
+
+    /// Runs `request` on the primary provider then on the fallbacks until one succeeds, following the retry policy
+    ///
+    /// Returns the output with the index of the provider that produced it and the number of attempts
+    async fn with_providers<'a, T, F, Fut>(&'a self, request: F) -> Result<(T, usize, usize), LensError>
+    where
+        F: Fn(&'a IProxyInstance<P, N>) -> Fut,
+        Fut: Future<Output = Result<T, LensError>>
+    {
+        let mut attempts = 0;
+        let mut error = None;
+        for (index, proxy) in iter::once(&self.proxy).chain(&self.fallbacks).enumerate() {
+            for retry in 0..=self.retry_policy.retries {
+                if retry > 0 {
+                    tokio::time::sleep(self.retry_policy.delay(retry - 1)).await;
+                }
+                attempts += 1;
+
+                let output = match self.retry_policy.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, request(proxy))
+                        .await
+                        .unwrap_or(Err(LensError::Timeout(timeout))),
+                    None => request(proxy).await,
+                };
+
+                match output {
+                    Ok(output) => return Ok((output, index, attempts)),
+                    Err(err) if err.is_retryable() => error = Some(err),
+                    // The node rejected the request or silently ignored its state overrides, retrying won't help
+                    Err(err @ (
+                        LensError::Transport(_)
+                        | LensError::MalformedOutput(_)
+                        | LensError::MalformedEnvelope { .. }
+                        | LensError::ResultCountMismatch { .. }
+                    )) => {
+                        error = Some(err);
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        Err(error.expect("at least one attempt is made"))
+    }
+
+    /// Executes the calls in `range` on the provider of `proxy` in a single `eth_call`,
+    /// plus one measuring their gas if needed
+    async fn try_chunk(&self, proxy: &IProxyInstance<P, N>, range: Range<usize>, block: BlockId) -> Result<Vec<CallResult>, LensError> {
+        if self.backend == Backend::Multicall3 {
+            return self.execute_multicall(proxy, range, block).await;
+        }
+
+        let calls = &self.calls[range.clone()];
+
+        let arguments = calls.iter().map(|elt| elt.encode()).collect();
+        let (calldata, output) = self.eth_call(proxy, self.mode, arguments, self.chunk_overrides(calls, 1), block).await?;
+        let mut results = self.decode_chunk(range.clone(), &calldata, &output)?;
+
+        if let Some((arguments, runs)) = self.measurement_pass(calls) {
+            let (_, output) = self.eth_call(proxy, ExecutionMode::Isolated, arguments, self.chunk_overrides(calls, runs), block).await?;
+            self.apply_measurement(range, &mut results, &output)?;
+        }
+
+        Ok(results)
+    }
+
+    /// Executes the calls in `range` through Multicall3 `aggregate3` in a single `eth_call`
+    /// on the provider of `proxy`
+    async fn execute_multicall(&self, proxy: &IProxyInstance<P, N>, range: Range<usize>, block: BlockId) -> Result<Vec<CallResult>, LensError> {
+        let calls = &self.calls[range.clone()];
+        let multicall = IMulticall3::new(MULTICALL3_ADDRESS, proxy.provider());
+
+        let arguments = calls.iter()
+            .map(|elt| IMulticall3::Call3 { target: elt.target(), allowFailure: true, callData: elt.argument().clone() })
             .collect();
+        let builder = multicall.aggregate3(arguments).block(block);
+        let key = self.cache_key(block, builder.calldata(), &StateOverride::default());
+        let output = match key.as_ref().and_then(|elt| self.cached(elt)) {
+            Some(output) => output,
+            None => {
+                let mut eth_call = builder.call_raw();
+                if let Some(overrides) = self.block_overrides.clone().filter(|elt| !elt.is_empty()) {
+                    eth_call = eth_call.with_block_overrides(overrides);
+                }
+                let output = eth_call.await?;
+                self.store(key, &output);
+                output
+            }
+        };
+
+        let results = IMulticall3::aggregate3Call::abi_decode_returns(&output)
+            .map_err(|_| LensError::MalformedOutput(output.clone()))?;
+        if results.len() != calls.len() {
+            return Err(LensError::ResultCountMismatch { expected: calls.len(), actual: results.len() });
+        }
+
+        Ok(
+            calls.iter()
+                .zip(results)
+                .map(|(c, elt)| CallResult::from_output(c, elt.success, elt.returnData, &self.errors))
+                .collect()
+        )
+    }
+
+    /// Sends `arguments` to the entry point of `mode` of `proxy`, returning the calldata and the raw output
+    async fn eth_call(
+        &self,
+        proxy: &IProxyInstance<P, N>,
+        mode: ExecutionMode,
+        arguments: Vec<IProxy::CallArgument>,
+        state_overrides: StateOverride,
+        block: BlockId,
+    ) -> Result<(Bytes, Bytes), LensError> {
+        let builder = match mode {
+            ExecutionMode::Isolated => proxy.execute(arguments).clear_decoder(),
+            ExecutionMode::Sequential => proxy.executeSequential(arguments).clear_decoder(),
+        };
+
+        let key = self.cache_key(block, builder.calldata(), &state_overrides);
+        if let Some(output) = key.as_ref().and_then(|elt| self.cached(elt)) {
+            return Ok((builder.calldata().clone(), output));
+        }
+
+        let builder = builder
+            .state(state_overrides)
+            .block(block);
+        let mut eth_call = builder.call_raw();
+        if let Some(overrides) = self.block_overrides.clone().filter(|elt| !elt.is_empty()) {
+            eth_call = eth_call.with_block_overrides(overrides);
+        }
 
-        let result = self.proxy.execute(calls).state(self.state_overrides.clone()).call().await.unwrap();
+        let output = eth_call.await?;
+        self.store(key, &output);
 
-        self.calls.iter()
-            .zip(result.iter())
-            .map(|(c, elt)| CallResult::from(c, elt))
+        Ok((builder.calldata().clone(), output))
+    }
+
+    /// Cache key of the `eth_call` sending `calldata` with `state_overrides` at `block`,
+    /// `None` without cache or if `block` isn't a block hash
+    fn cache_key(&self, block: BlockId, calldata: &Bytes, state_overrides: &StateOverride) -> Option<CacheKey> {
+        let BlockId::Hash(hash) = block else {
+            return None;
+        };
+        self.cache.as_ref()?;
+
+        Some(CacheKey::new(hash.block_hash, calldata.clone(), state_overrides, self.block_overrides.as_ref()))
+    }
+
+    /// Cached output of the `eth_call` identified by `key`
+    fn cached(&self, key: &CacheKey) -> Option<Bytes> {
+        self.cache.as_ref()?.get(key)
+    }
+
+    /// Caches the `output` of the `eth_call` identified by `key`, if any
+    fn store(&self, key: Option<CacheKey>, output: &Bytes) {
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            cache.insert(key, output.clone());
+        }
+    }
+
+    /// State override sent with a chunk made of `calls`, each of them running `runs` times,
+    /// including the proxy code and the forwarders of every sender of the batch
+    fn chunk_overrides(&self, calls: &[Call], runs: u64) -> StateOverride {
+        let mut state_overrides = self.state_overrides.clone();
+        let mut proxy = AccountOverride::default().with_code(PROXY_BYTECODE);
+        let value = calls.iter()
+            .fold(U256::ZERO, |acc, elt| acc.saturating_add(elt.value()))
+            .saturating_mul(U256::from(runs));
+        if !value.is_zero() {
+            proxy.balance = Some(value);
+        }
+        state_overrides.insert(*self.proxy.address(), proxy);
+        for sender in self.calls.iter().filter_map(|elt| elt.sender()) {
+            state_overrides.entry(sender).or_default().code = Some(forwarder_bytecode(self.proxy.address()));
+        }
+
+        state_overrides
+    }
+
+    /// Decodes the proxy output of the calls in `range`, sent with `calldata`
+    fn decode_chunk(&self, range: Range<usize>, calldata: &[u8], output: &[u8]) -> Result<Vec<CallResult>, LensError> {
+        let calls = &self.calls[range.clone()];
+        let IProxy::executeReturn { results, gasUsed } = IProxy::executeCall::abi_decode_returns(output)
+            .map_err(|_| LensError::MalformedOutput(Bytes::copy_from_slice(output)))?;
+
+        if results.len() != calls.len() {
+            return Err(LensError::ResultCountMismatch { expected: calls.len(), actual: results.len() });
+        }
+
+        let eth_call_gas = transaction_gas(calldata, gasUsed);
+        calls.iter()
+            .zip(results.iter())
+            .zip(range)
+            .map(|((c, elt), index)| {
+                let mut result = CallResult::from(index, c, elt, &self.errors)?;
+                result.eth_call_gas = eth_call_gas;
+                // Isolated calls already run cold
+                if self.mode == ExecutionMode::Isolated && self.gas_measurement != GasMeasurement::Batch {
+                    result.cold_gas_used = Some(result.gas_used);
+                }
+                Ok(result)
+            })
             .collect()
     }
-}
\ No newline at end of file
+
+    /// Proxy arguments of the isolated execution measuring the gas of `calls`, if one is needed,
+    /// and the number of times each call runs in it
+    fn measurement_pass(&self, calls: &[Call]) -> Option<(Vec<IProxy::CallArgument>, u64)> {
+        match (self.gas_measurement, self.mode) {
+            (GasMeasurement::Batch, _) | (GasMeasurement::Cold, ExecutionMode::Isolated) => None,
+            (GasMeasurement::Cold, ExecutionMode::Sequential) => Some((calls.iter().map(Call::encode).collect(), 1)),
+            // The proxy calls itself to run each call twice in a row, reverting both runs afterwards
+            (GasMeasurement::ColdAndWarm, _) => {
+                let arguments = calls.iter()
+                    .map(|elt| IProxy::CallArgument {
+                        callee: *self.proxy.address(),
+                        argument: IProxy::executeSequentialCall::new((vec![elt.encode(), elt.encode()],)).abi_encode().into(),
+                        value: U256::ZERO,
+                        gas: U256::ZERO,
+                    })
+                    .collect();
+                Some((arguments, 2))
+            }
+        }
+    }
+
+    /// Whether the gas of the calls is measured in a separate `eth_call`
+    fn is_measured(&self) -> bool {
+        !matches!(
+            (self.gas_measurement, self.mode),
+            (GasMeasurement::Batch, _) | (GasMeasurement::Cold, ExecutionMode::Isolated)
+        )
+    }
+
+    /// Sets the cold and warm gas of the calls in `range` from the output of their measurement pass
+    ///
+    /// A call whose cold run doesn't succeed or return as its actual run, e.g. a sequential call
+    /// depending on the previous ones, measured on the initial state, is left unmeasured
+    fn apply_measurement(&self, range: Range<usize>, results: &mut [CallResult], output: &[u8]) -> Result<(), LensError> {
+        let IProxy::executeReturn { results: envelopes, .. } = IProxy::executeCall::abi_decode_returns(output)
+            .map_err(|_| LensError::MalformedOutput(Bytes::copy_from_slice(output)))?;
+
+        if envelopes.len() != results.len() {
+            return Err(LensError::ResultCountMismatch { expected: results.len(), actual: envelopes.len() });
+        }
+
+        for ((result, envelope), index) in results.iter_mut().zip(envelopes.iter()).zip(range) {
+            let (success, output, cold, warm) = measured_gas(self.gas_measurement, envelope)
+                .ok_or_else(|| LensError::MalformedEnvelope { index, data: envelope.clone() })?;
+            if success == result.success && output == result.output[..] {
+                result.cold_gas_used = Some(cold);
+                result.warm_gas_used = warm;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the batch can be executed before sending anything
+    fn validate(&self) -> Result<(), LensError> {
+        let proxy = *self.proxy.address();
+        if self.state_overrides.contains_key(&proxy)
+            || self.calls.iter().any(|elt| elt.target() == proxy || elt.sender() == Some(proxy))
+        {
+            return Err(LensError::ProxyCollision(proxy));
+        }
+
+        for sender in self.calls.iter().filter_map(|elt| elt.sender()) {
+            if self.state_overrides.get(&sender).is_some_and(|elt| elt.code.is_some()) {
+                return Err(LensError::EphemeralSender(sender));
+            }
+            if self.calls.iter().any(|elt| elt.target() == sender) {
+                return Err(LensError::SenderCalled(sender));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the batch can be executed through the selected backend
+    fn validate_backend(&self) -> Result<(), LensError> {
+        match self.backend {
+            Backend::Proxy => self.validate(),
+            Backend::Multicall3 => self.validate_multicall(),
+        }
+    }
+
+    /// Checks the batch only uses features Multicall3 supports
+    fn validate_multicall(&self) -> Result<(), LensError> {
+        let feature = if !self.state_overrides.is_empty() {
+            Some("state overrides")
+        } else if self.calls.iter().any(|elt| elt.sender().is_some()) {
+            Some("call senders")
+        } else if self.calls.iter().any(|elt| !elt.value().is_zero()) {
+            Some("call value")
+        } else if self.calls.iter().any(|elt| !elt.gas().is_zero()) {
+            Some("call gas limits")
+        } else if self.gas_measurement != GasMeasurement::Batch {
+            Some("gas measurement")
+        } else {
+            None
+        };
+
+        match feature {
+            Some(feature) => Err(LensError::Unsupported { backend: Backend::Multicall3, feature }),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolves `block` to the block id sent with the `eth_call` and its number
+    async fn resolve_block(&self, block: Option<BlockId>) -> Result<(BlockId, Option<u64>), LensError> {
+        let Some(block) = block else {
+            return Ok((BlockId::latest(), None));
+        };
+
+        if block.is_pending() {
+            return Ok((block, None));
+        }
+
+        let (response, ..) = self.with_providers(|proxy| async move {
+            proxy.provider()
+                .get_block(block)
+                .await
+                .map_err(|err| LensError::Transport(err.into()))
+        }).await?;
+        let response = response.ok_or(LensError::BlockNotFound(block))?;
+        let header = response.header();
+
+        Ok((BlockId::hash(header.hash()), Some(header.number())))
+    }
+}
+
+/// Calldata of the proxy entry point of `mode` executing `arguments`
+#[cfg(feature = "revm")]
+fn proxy_calldata(mode: ExecutionMode, arguments: Vec<IProxy::CallArgument>) -> Bytes {
+    match mode {
+        ExecutionMode::Isolated => IProxy::executeCall::new((arguments,)).abi_encode().into(),
+        ExecutionMode::Sequential => IProxy::executeSequentialCall::new((arguments,)).abi_encode().into(),
+    }
+}
+
+/// Success and output of the cold run of a call, then its cold and warm gas, from its envelope
+/// in the measurement pass
+fn measured_gas(gas_measurement: GasMeasurement, envelope: &[u8]) -> Option<(bool, Vec<u8>, U256, Option<U256>)> {
+    let (success, gas_used, _, output) = decode_envelope(envelope)?;
+    if gas_measurement != GasMeasurement::ColdAndWarm {
+        return Some((success, output, gas_used, None));
+    }
+
+    // The envelope wraps the output of `executeSequential` running the call twice
+    if !success {
+        return None;
+    }
+    let runs = IProxy::executeSequentialCall::abi_decode_returns(&output).ok()?.results;
+    let [cold, warm] = runs.as_slice() else {
+        return None;
+    };
+
+    let (success, cold_gas_used, _, output) = decode_envelope(cold)?;
+    Some((success, output, cold_gas_used, Some(decode_envelope(warm)?.1)))
+}
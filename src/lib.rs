@@ -1,8 +1,38 @@
+mod cache;
 mod contract;
 mod call_result;
 mod lens;
 mod call;
+mod diff;
+mod error;
+mod gas;
+mod handle;
+mod limits;
+mod mode;
+mod plan;
+mod policy;
+mod probe;
+mod revert;
+mod series;
+mod signature;
+#[cfg(feature = "revm")]
+mod local;
 
 pub use lens::Lens;
+pub use cache::{CacheKey, LensCache, LruCache};
 pub use call::Call;
-pub use call_result::CallResult;
\ No newline at end of file
+pub use call_result::CallResult;
+pub use diff::{CallDiff, FieldDiff};
+pub use error::LensError;
+pub use handle::{CallFailure, Handle};
+pub use limits::BatchLimits;
+pub use mode::{Backend, ExecutionMode, GasMeasurement};
+pub use plan::LensPlan;
+pub use policy::RetryPolicy;
+pub use probe::Capabilities;
+pub use revert::RevertReason;
+pub use series::BlockSeries;
+pub use signature::SignatureArg;
+/// Re-export of the revm version [`Lens::call_local`] runs on, e.g. for its databases
+#[cfg(feature = "revm")]
+pub use revm;
@@ -0,0 +1,125 @@
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::call::Call;
+
+
+/// Limits used to split a lens batch into several `eth_call`s
+///
+/// A chunk is closed as soon as adding the next call would exceed one of the limits,
+/// a single call exceeding a limit on its own still gets a chunk of its own.
+///
+/// # Example
+/// ```
+/// # use alloy_ephemeral_lens::BatchLimits;
+/// let limits = BatchLimits::default()
+///     .with_max_calls(500)
+///     .with_max_gas(30_000_000)
+///     .with_concurrency(8);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchLimits {
+    /// Maximum number of calls per chunk
+    pub max_calls: Option<usize>,
+    /// Maximum size of the encoded `IProxy::execute` calldata per chunk
+    pub max_calldata_bytes: Option<usize>,
+    /// Maximum estimated gas per chunk
+    pub max_gas: Option<u64>,
+    /// Gas estimate of a call without an explicit gas limit
+    pub default_call_gas: u64,
+    /// Maximum number of chunks executed concurrently
+    pub concurrency: usize,
+}
+
+impl Default for BatchLimits {
+    /// No limit, the whole batch is sent in a single `eth_call`
+    fn default() -> Self {
+        Self {
+            max_calls: None,
+            max_calldata_bytes: None,
+            max_gas: None,
+            default_call_gas: 1_000_000,
+            concurrency: 4,
+        }
+    }
+}
+
+impl BatchLimits {
+    /// Sets the maximum number of calls per chunk
+    pub fn with_max_calls(mut self, max_calls: usize) -> Self {
+        self.max_calls = Some(max_calls);
+        self
+    }
+
+    /// Sets the maximum calldata size per chunk
+    pub fn with_max_calldata_bytes(mut self, max_calldata_bytes: usize) -> Self {
+        self.max_calldata_bytes = Some(max_calldata_bytes);
+        self
+    }
+
+    /// Sets the maximum estimated gas per chunk
+    pub fn with_max_gas(mut self, max_gas: u64) -> Self {
+        self.max_gas = Some(max_gas);
+        self
+    }
+
+    /// Sets the gas estimate of calls without an explicit gas limit
+    pub fn with_default_call_gas(mut self, default_call_gas: u64) -> Self {
+        self.default_call_gas = default_call_gas;
+        self
+    }
+
+    /// Sets the maximum number of chunks executed concurrently
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Splits `calls` into the consecutive ranges sent in a single `eth_call` each
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy::{json_abi::Function, primitives::{Address, Bytes}};
+    /// # use alloy_ephemeral_lens::{BatchLimits, Call};
+    /// let decimals = Function::parse("decimals() returns (uint8)").unwrap();
+    /// let calls = vec![Call::new(decimals, Address::ZERO, Bytes::new()); 5];
+    ///
+    /// assert_eq!(BatchLimits::default().with_max_calls(2).chunks(&calls), [0..2, 2..4, 4..5]);
+    /// ```
+    pub fn chunks(&self, calls: &[Call]) -> Vec<Range<usize>> {
+        let mut chunks = vec![];
+        let mut start = 0;
+        let mut calldata_bytes = EXECUTE_BASE_SIZE;
+        let mut gas = 0u64;
+
+        for (index, call) in calls.iter().enumerate() {
+            let call_bytes = call.encoded_size();
+            let call_gas = call.gas_estimate(self.default_call_gas);
+
+            let exceeds = self.max_calls.is_some_and(|max| index - start >= max)
+                || self.max_calldata_bytes.is_some_and(|max| calldata_bytes + call_bytes > max)
+                || self.max_gas.is_some_and(|max| gas.saturating_add(call_gas) > max);
+
+            if exceeds && index > start {
+                chunks.push(start..index);
+                start = index;
+                calldata_bytes = EXECUTE_BASE_SIZE;
+                gas = 0;
+            }
+
+            calldata_bytes += call_bytes;
+            gas = gas.saturating_add(call_gas);
+        }
+
+        if start < calls.len() {
+            chunks.push(start..calls.len());
+        }
+
+        chunks
+    }
+}
+
+/// Selector, array offset and array length of the `execute` calldata
+const EXECUTE_BASE_SIZE: usize = 4 + 32 + 32;
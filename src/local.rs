@@ -0,0 +1,116 @@
+use alloy::{
+    primitives::{Address, Bytes, TxKind, U256},
+    rpc::types::{state::StateOverride, BlockOverrides},
+};
+
+use revm::{
+    bytecode::Bytecode,
+    context::{BlockEnv, TxEnv},
+    context_interface::result::ExecutionResult,
+    database::CacheDB,
+    Context, Database, DatabaseRef, ExecuteEvm, MainBuilder, MainContext,
+};
+
+use crate::LensError;
+
+/// Gas limit of the local transaction, matching the default `eth_call` gas cap of geth
+const CALL_GAS_LIMIT: u64 = 50_000_000;
+
+/// Executes `calldata` against `proxy` in an embedded revm instance on top of `db`
+///
+/// The state override is applied to an in-memory layer, `db` itself is never written to
+pub(crate) fn execute<DB: DatabaseRef>(
+    db: DB,
+    proxy: Address,
+    calldata: Bytes,
+    state_overrides: &StateOverride,
+    block_overrides: Option<&BlockOverrides>,
+) -> Result<Bytes, LensError> {
+    let mut cache = CacheDB::new(db);
+    apply_state_overrides(&mut cache, state_overrides).map_err(|err| LensError::Evm(err.to_string()))?;
+
+    let mut evm = Context::mainnet()
+        .with_db(cache)
+        .modify_cfg_chained(|cfg| {
+            cfg.disable_nonce_check = true;
+            cfg.disable_balance_check = true;
+            cfg.disable_base_fee = true;
+        })
+        .modify_block_chained(|block| {
+            if let Some(overrides) = block_overrides {
+                apply_block_overrides(block, overrides);
+            }
+        })
+        .build_mainnet();
+
+    let tx = TxEnv {
+        kind: TxKind::Call(proxy),
+        data: calldata,
+        gas_limit: CALL_GAS_LIMIT,
+        ..Default::default()
+    };
+
+    match evm.transact(tx).map_err(|err| LensError::Evm(err.to_string()))?.result {
+        ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+        ExecutionResult::Revert { output, .. } => Err(LensError::Evm(format!("proxy reverted: {output}"))),
+        ExecutionResult::Halt { reason, .. } => Err(LensError::Evm(format!("proxy halted: {reason:?}"))),
+    }
+}
+
+/// Writes `overrides` into the in-memory layer of `cache`
+fn apply_state_overrides<DB: DatabaseRef>(cache: &mut CacheDB<DB>, overrides: &StateOverride) -> Result<(), DB::Error> {
+    for (address, account) in overrides {
+        let mut info = cache.basic(*address)?.unwrap_or_default();
+        if let Some(balance) = account.balance {
+            info.balance = balance;
+        }
+        if let Some(nonce) = account.nonce {
+            info.nonce = nonce;
+        }
+        if let Some(code) = &account.code {
+            let bytecode = Bytecode::new_raw(code.clone());
+            info.code_hash = bytecode.hash_slow();
+            info.code = Some(bytecode);
+        }
+        cache.insert_account_info(*address, info);
+
+        if let Some(state) = &account.state {
+            let storage = state.iter()
+                .map(|(slot, value)| (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0)))
+                .collect();
+            cache.replace_account_storage(*address, storage)?;
+        }
+        if let Some(state_diff) = &account.state_diff {
+            for (slot, value) in state_diff {
+                cache.insert_account_storage(*address, U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the overridden header fields into `block`
+fn apply_block_overrides(block: &mut BlockEnv, overrides: &BlockOverrides) {
+    if let Some(number) = overrides.number {
+        block.number = number;
+    }
+    if let Some(time) = overrides.time {
+        block.timestamp = U256::from(time);
+    }
+    if let Some(gas_limit) = overrides.gas_limit {
+        block.gas_limit = gas_limit;
+    }
+    if let Some(coinbase) = overrides.coinbase {
+        block.beneficiary = coinbase;
+    }
+    if let Some(random) = overrides.random {
+        block.prevrandao = Some(random);
+    }
+    if let Some(base_fee) = overrides.base_fee {
+        block.basefee = base_fee.saturating_to();
+    }
+    if let Some(difficulty) = overrides.difficulty {
+        block.difficulty = difficulty;
+    }
+}
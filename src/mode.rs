@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// How the proxy executes the calls of a batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExecutionMode {
+    /// Each call runs on the initial state, its state changes are reverted afterwards
+    #[default]
+    Isolated,
+    /// Calls run in registration order and keep their state changes,
+    /// so each call sees the effects of the previous ones.
+    /// A reverting call only rolls back its own changes.
+    Sequential,
+}
+
+/// Contract [`Lens::call`](crate::Lens::call) executes the batch through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backend {
+    /// The lens proxy, installed at execution time through a state override
+    #[default]
+    Proxy,
+    /// The canonical Multicall3 deployment and its `aggregate3`, for nodes rejecting state overrides
+    ///
+    /// Only plain calls to deployed contracts are supported: no state override, ephemeral
+    /// contract, sender, value, call gas limit or gas measurement. Calls share the state of
+    /// the batch as in [`ExecutionMode::Sequential`], which makes no difference for reads.
+    ///
+    /// Gas isn't measured, [`CallResult::gas_used`](crate::CallResult::gas_used) and
+    /// [`CallResult::eth_call_gas`](crate::CallResult::eth_call_gas) are zero and
+    /// [`CallResult::out_of_gas`](crate::CallResult::out_of_gas) is always `false`.
+    Multicall3,
+}
+
+/// How the gas of each call is measured, besides [`CallResult::gas_used`](crate::CallResult::gas_used)
+///
+/// Accounts and storage slots accessed by a call stay warm for the rest of the transaction
+/// unless its frame reverts (EIP-2929). Isolated calls revert their frame so they always start cold,
+/// sequential calls share the warm accounts and slots of the previous ones and look cheaper the later they run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GasMeasurement {
+    /// Gas is only measured within the batch execution
+    #[default]
+    Batch,
+    /// Each call is also measured cold, as if it were the only call of a fresh transaction
+    ///
+    /// Sequential batches are measured by an extra isolated execution, on the initial state.
+    /// Calls whose measured run doesn't succeed or return as their actual run, e.g. because
+    /// they depend on previous calls, are left unmeasured.
+    Cold,
+    /// Each call is measured cold, then run a second time right after, with warm accounts and slots
+    ///
+    /// Requires an extra execution of the batch in which each call runs twice, the second run
+    /// sees the state changes of the first one. The first run is isolated, on the initial state,
+    /// calls for which it doesn't succeed or return as their actual run are left unmeasured.
+    ColdAndWarm,
+}
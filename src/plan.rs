@@ -0,0 +1,56 @@
+use alloy::{
+    eips::BlockId,
+    json_abi::Error,
+    primitives::Address,
+    rpc::types::{state::StateOverride, BlockOverrides},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Backend, BatchLimits, Call, ExecutionMode, GasMeasurement};
+
+
+/// Serializable snapshot of a [`Lens`](crate::Lens) batch
+///
+/// Holds everything needed to execute the batch again and decode its results: the calldata,
+/// target, value, gas and sender of each call with its function ABI, the overrides, the block,
+/// the execution settings and the registered custom errors.
+///
+/// # Example
+/// ```
+/// # use alloy_ephemeral_lens::{Lens, LensPlan};
+/// # use alloy::providers::ProviderBuilder;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// # let provider = ProviderBuilder::new().connect("http://localhost:8080").await?;
+/// # let lens = Lens::new(&provider);
+/// let json = serde_json::to_string(&lens.plan())?;
+///
+/// // Later, possibly on another service
+/// let plan: LensPlan = serde_json::from_str(&json)?;
+/// let results = Lens::from_plan(&provider, plan).call().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LensPlan {
+    /// Address the proxy is installed at
+    pub proxy: Address,
+    /// Calls of the batch, in registration order
+    pub calls: Vec<Call>,
+    /// User state overrides, without the proxy and forwarder code
+    pub state_overrides: StateOverride,
+    /// Block the batch is executed at, the node's latest block if unset
+    pub block: Option<BlockId>,
+    /// Header fields overridden for execution
+    pub block_overrides: Option<BlockOverrides>,
+    /// Limits used to split the batch into several `eth_call`s
+    pub limits: BatchLimits,
+    /// How the proxy executes the calls
+    pub mode: ExecutionMode,
+    /// How the gas of each call is measured
+    pub gas_measurement: GasMeasurement,
+    /// Contract the batch is executed through
+    #[serde(default)]
+    pub backend: Backend,
+    /// Custom errors used to decode reverts
+    pub errors: Vec<Error>,
+}
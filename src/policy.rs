@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+
+/// Timeout and retries applied to each request of [`Lens::call`](crate::Lens::call) on each provider
+///
+/// A request failing with a transport error or timing out is retried on the same provider
+/// after an exponentially growing delay. Once the retries are exhausted, or right away if
+/// the node rejects the request (e.g. its state overrides) or returns a malformed output
+/// (e.g. ignoring them), the next fallback provider is tried.
+///
+/// # Example
+/// ```
+/// # use std::time::Duration;
+/// # use alloy_ephemeral_lens::RetryPolicy;
+/// let policy = RetryPolicy::default()
+///     .with_timeout(Duration::from_secs(5))
+///     .with_retries(3)
+///     .with_backoff(Duration::from_millis(200));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum duration of an attempt, unbounded if unset
+    pub timeout: Option<Duration>,
+    /// Number of retries on the same provider
+    pub retries: usize,
+    /// Delay before the first retry, doubled for each following one
+    pub backoff: Duration,
+    /// Maximum delay between two retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// A single attempt per provider without timeout
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            retries: 0,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sets the maximum duration of an attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number of retries on the same provider
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the delay before the first retry
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the maximum delay between two retries
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Delay before the retry following `retry` previous ones
+    pub(super) fn delay(&self, retry: usize) -> Duration {
+        let factor = 1u32.checked_shl(retry.try_into().unwrap_or(u32::MAX)).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
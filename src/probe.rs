@@ -0,0 +1,113 @@
+use alloy::{
+    contract::{self, RawCallBuilder},
+    eips::BlockId,
+    network::Network,
+    primitives::{address, hex, Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::{state::{AccountOverride, StateOverride}, BlockOverrides},
+    transports::RpcError,
+};
+
+use crate::{Backend, LensError};
+
+
+/// Address the state override probe installs its code at
+const PROBE_ADDRESS: Address = address!("0x00000000000000000000000000000000000c0ffe");
+
+/// Runtime code returning 42, without PUSH0
+const ANSWER_BYTECODE: [u8; 10] = hex!("602a60005260206000f3");
+
+/// Init code returning 42 as runtime code, using PUSH0
+const PUSH0_INITCODE: [u8; 8] = hex!("602a5f5260205ff3");
+
+/// Init code returning the block timestamp as runtime code, without PUSH0
+const TIMESTAMP_INITCODE: [u8; 9] = hex!("4260005260206000f3");
+
+/// Timestamp the block override probe sets
+const PROBE_TIMESTAMP: u64 = 4_102_444_800;
+
+/// Features of a node the lens relies on, reported by [`Lens::probe`](crate::Lens::probe)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `eth_call` applies state overrides, required by [`Backend::Proxy`]
+    pub state_overrides: bool,
+    /// `eth_call` applies block overrides, required by [`Lens::with_block_overrides`](crate::Lens::with_block_overrides)
+    pub block_overrides: bool,
+    /// Number of blocks behind the head the historical state was probed at
+    pub historical_depth: u64,
+    /// State at [`historical_depth`](Self::historical_depth) blocks behind the head can be read
+    pub historical_state: bool,
+    /// The EVM supports `PUSH0`, required by the proxy bytecode
+    pub push0: bool,
+}
+
+impl Capabilities {
+    /// Earliest EVM version the proxy bytecode runs on, as it uses `PUSH0`
+    pub const PROXY_EVM_VERSION: &'static str = "shanghai";
+
+    /// Backend able to execute batches on the node, [`Backend::Multicall3`] if the proxy can't run
+    pub fn backend(&self) -> Backend {
+        if self.state_overrides && self.push0 {
+            Backend::Proxy
+        } else {
+            Backend::Multicall3
+        }
+    }
+}
+
+/// Probes the features of the node behind `provider`, reading state `depth` blocks behind the head
+pub(crate) async fn probe<P, N>(provider: &P, depth: u64) -> Result<Capabilities, LensError>
+where
+    N: Network,
+    P: Provider<N>
+{
+    let overrides = StateOverride::from_iter([
+        (PROBE_ADDRESS, AccountOverride::default().with_code(Bytes::from(ANSWER_BYTECODE)))
+    ]);
+    let state_overrides = accepted(
+        RawCallBuilder::new_raw(provider, Bytes::new())
+            .to(PROBE_ADDRESS)
+            .state(overrides)
+            .call_raw()
+            .await
+    )?.is_some_and(|output| returns(&output, U256::from(42)));
+
+    let block_overrides = accepted(
+        RawCallBuilder::new_raw_deploy(provider, Bytes::from(TIMESTAMP_INITCODE))
+            .call_raw()
+            .with_block_overrides(BlockOverrides::default().with_time(PROBE_TIMESTAMP))
+            .await
+    )?.is_some_and(|output| returns(&output, U256::from(PROBE_TIMESTAMP)));
+
+    let push0 = accepted(
+        RawCallBuilder::new_raw_deploy(provider, Bytes::from(PUSH0_INITCODE))
+            .call_raw()
+            .await
+    )?.is_some_and(|output| returns(&output, U256::from(42)));
+
+    let head = provider.get_block_number()
+        .await
+        .map_err(|err| LensError::Transport(err.into()))?;
+    let historical_state = accepted(
+        provider.get_balance(Address::ZERO)
+            .block_id(BlockId::number(head.saturating_sub(depth)))
+            .await
+            .map_err(contract::Error::from)
+    )?.is_some();
+
+    Ok(Capabilities { state_overrides, block_overrides, historical_depth: depth, historical_state, push0 })
+}
+
+/// Output of a probe request, `None` if the node answered with an error
+fn accepted<T>(result: Result<T, contract::Error>) -> Result<Option<T>, LensError> {
+    match result {
+        Ok(output) => Ok(Some(output)),
+        Err(contract::Error::TransportError(RpcError::ErrorResp(_))) => Ok(None),
+        Err(err) => Err(LensError::Transport(err)),
+    }
+}
+
+/// Whether `output` is the single word `value`
+fn returns(output: &[u8], value: U256) -> bool {
+    output.len() == 32 && U256::from_be_slice(output) == value
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt},
+    json_abi::Error,
+    primitives::Bytes,
+    sol_types::{Panic, Revert, SolError, SolInterface},
+};
+
+
+/// Reason a call reverted, decoded from its revert data
+#[derive(Debug, Clone, PartialEq)]
+pub enum RevertReason {
+    /// `Error(string)` raised by `require` or `revert("...")`
+    Error(Revert),
+    /// `Panic(uint256)` raised by failed assertions, arithmetic overflows, ...
+    Panic(Panic),
+    /// Custom error matching an error ABI registered on the [`Lens`](crate::Lens)
+    Custom {
+        /// Name of the error
+        name: String,
+        /// Decoded error arguments
+        args: Vec<DynSolValue>,
+        /// Raw revert data, selector included
+        data: Bytes,
+    },
+    /// Revert data matching no known error, empty for a bare `revert()`
+    Raw(Bytes),
+}
+
+impl RevertReason {
+    /// Decodes revert data, trying `Error(string)`, `Panic(uint256)` then the registered `errors`
+    pub(crate) fn decode(data: &[u8], errors: &[Error]) -> Self {
+        if let Ok(revert) = Revert::abi_decode(data) {
+            return Self::Error(revert);
+        }
+        if let Ok(panic) = Panic::abi_decode(data) {
+            return Self::Panic(panic);
+        }
+
+        let custom = data.get(..4).and_then(|selector| {
+            errors.iter()
+                .filter(|elt| elt.selector() == selector)
+                .find_map(|elt| Some((elt, elt.abi_decode_input(&data[4..]).ok()?)))
+        });
+
+        match custom {
+            Some((error, args)) => Self::Custom { name: error.name.clone(), args, data: data.to_vec().into() },
+            None => Self::Raw(data.to_vec().into()),
+        }
+    }
+
+    /// Raw revert data
+    pub fn data(&self) -> Bytes {
+        match self {
+            Self::Error(revert) => revert.abi_encode().into(),
+            Self::Panic(panic) => panic.abi_encode().into(),
+            Self::Custom { data, .. } | Self::Raw(data) => data.clone(),
+        }
+    }
+
+    /// Decodes the revert data into a `sol!` generated error enum
+    ///
+    /// # Example
+    /// ```
+    /// # use alloy_ephemeral_lens::RevertReason;
+    /// # use alloy::{primitives::Bytes, sol, sol_types::SolError};
+    /// sol! {
+    ///     #[derive(Debug, PartialEq)]
+    ///     interface IPool {
+    ///         error InsufficientLiquidity();
+    ///     }
+    /// }
+    ///
+    /// # let reason = RevertReason::Raw(Bytes::from(IPool::InsufficientLiquidity {}.abi_encode()));
+    /// let error = reason.decode_as::<IPool::IPoolErrors>();
+    /// assert!(matches!(error, Some(IPool::IPoolErrors::InsufficientLiquidity(_))));
+    /// ```
+    pub fn decode_as<E: SolInterface>(&self) -> Option<E> {
+        E::abi_decode(&self.data()).ok()
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(revert) => write!(f, "{revert}"),
+            Self::Panic(panic) => write!(f, "{panic}"),
+            Self::Custom { name, args, .. } => write!(f, "custom error {name}{args:?}"),
+            Self::Raw(data) if data.is_empty() => write!(f, "empty revert data"),
+            Self::Raw(data) => write!(f, "unknown revert data {data}"),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::ops::RangeInclusive;
+
+
+/// Blocks a lens batch is executed at by [`Lens::call_series`](crate::Lens::call_series)
+///
+/// # Example
+/// ```
+/// # use alloy_ephemeral_lens::BlockSeries;
+/// // Every 100th block of the range, 8 blocks at a time
+/// let series = BlockSeries::new(19_000_000..=19_100_000, 100)
+///     .with_retries(3)
+///     .with_concurrency(8);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSeries {
+    /// First and last block of the series
+    pub range: RangeInclusive<u64>,
+    /// Number of blocks between two executions, at least one
+    pub step: u64,
+    /// Number of times a block is executed again after a retryable error, waiting
+    /// the delays of the lens [`RetryPolicy`](crate::RetryPolicy) in between
+    pub retries: usize,
+    /// Maximum number of blocks executed concurrently
+    pub concurrency: usize,
+}
+
+impl BlockSeries {
+    /// Executes the batch every `step` blocks of `range`, starting at its first block
+    pub fn new(range: RangeInclusive<u64>, step: u64) -> Self {
+        Self { range, step: step.max(1), retries: 0, concurrency: 4 }
+    }
+
+    /// Sets the number of retries after a transport error
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the maximum number of blocks executed concurrently
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Numbers of the blocks of the series, in ascending order
+    pub(super) fn blocks(&self) -> impl Iterator<Item = u64> {
+        self.range.clone().step_by(self.step.max(1).try_into().unwrap_or(usize::MAX))
+    }
+}
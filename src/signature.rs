@@ -0,0 +1,55 @@
+use alloy::{
+    dyn_abi::{self, DynSolValue, Specifier},
+    json_abi::Function,
+};
+
+
+/// Argument of a call registered from a human-readable signature
+///
+/// Strings are parsed against the matching input type of the signature,
+/// e.g. `"0xC02a…6Cc2"` for an `address` or `"1000000"` for a `uint256`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureArg {
+    /// Value parsed against the input type
+    Str(String),
+    /// Value used as is
+    Value(DynSolValue),
+}
+
+impl From<&str> for SignatureArg {
+    fn from(value: &str) -> Self {
+        Self::Str(value.to_string())
+    }
+}
+
+impl From<String> for SignatureArg {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<DynSolValue> for SignatureArg {
+    fn from(value: DynSolValue) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// Resolves `args` against the inputs of `function`, parsing string arguments
+pub(crate) fn resolve_args(function: &Function, args: Vec<SignatureArg>) -> Result<Vec<DynSolValue>, dyn_abi::Error> {
+    if args.len() != function.inputs.len() {
+        return Err(dyn_abi::Error::custom(format!(
+            "{} expects {} arguments, got {}",
+            function.signature(),
+            function.inputs.len(),
+            args.len()
+        )));
+    }
+
+    function.inputs.iter()
+        .zip(args)
+        .map(|(param, arg)| match arg {
+            SignatureArg::Str(value) => param.resolve()?.coerce_str(&value),
+            SignatureArg::Value(value) => Ok(value),
+        })
+        .collect()
+}
@@ -1,11 +1,19 @@
 use std::env;
 
 use alloy::{
-    primitives::{address, Address, U256},
-    providers::{ProviderBuilder, WsConnect},
+    eips::BlockId,
+    dyn_abi::DynSolValue,
+    json_abi::{Function, JsonAbi},
+    rpc::types::BlockOverrides,
+    primitives::{address, keccak256, Address, Bytes, B256, U256},
+    providers::{ProviderBuilder, RootProvider, WsConnect},
     sol,
+    sol_types::SolValue,
+    transports::mock::Asserter,
 };
-use alloy_ephemeral_lens::Lens;
+use alloy_ephemeral_lens::{Backend, BatchLimits, BlockSeries, CallFailure, ExecutionMode, GasMeasurement, Lens, LensError, LensPlan, RetryPolicy, RevertReason};
+#[cfg(feature = "revm")]
+use revm::database::{CacheDB, EmptyDB};
 
 // Direct ERC20 interface — no bytecode, calls go straight to mainnet contracts
 sol! {
@@ -19,6 +27,84 @@ sol! {
     }
 }
 
+sol! {
+    interface IWETH {
+        #[sol(abi)]
+        function deposit() external payable;
+        #[sol(abi)]
+        function balanceOf(address) external view returns (uint256);
+    }
+}
+
+// Ephemeral lens returning header fields, hand-written runtime code ignoring calldata:
+// TIMESTAMP, NUMBER, BASEFEE, COINBASE, PREVRANDAO stored in order and returned
+sol! {
+    #[sol(deployed_bytecode="425f524360205248604052416060524460805260a05ff3")]
+    interface IBlockLens {
+        #[sol(abi)]
+        function blockInfo() external view returns (uint256, uint256, uint256, address, bytes32);
+    }
+}
+
+// Ephemeral counter, hand-written runtime code ignoring calldata:
+// increments slot 0 and returns its new value
+sol! {
+    #[sol(deployed_bytecode="5f54600101805f555f5260205ff3")]
+    interface ICounter {
+        #[sol(abi)]
+        function increment() external returns (uint256);
+    }
+}
+
+// Ephemeral contract looping forever, hand-written runtime code
+sol! {
+    #[sol(deployed_bytecode="5b5f56")]
+    interface ILoop {
+        #[sol(abi)]
+        function spin() external;
+    }
+}
+
+// Ephemeral lens returning its caller and the value it received, hand-written runtime code ignoring calldata
+sol! {
+    #[sol(deployed_bytecode="335f523460205260405ff3")]
+    interface IContext {
+        #[sol(abi)]
+        function context() external payable returns (address, uint256);
+    }
+}
+
+// Ephemeral lens returning the code size of an account, hand-written runtime code
+sol! {
+    #[sol(deployed_bytecode="6004353b5f5260205ff3")]
+    interface ICodeSize {
+        #[sol(abi)]
+        function codeSize(address account) external view returns (uint256);
+    }
+}
+
+// Ephemeral contract reverting with its calldata, hand-written runtime code.
+// Each function shares its selector with the error of the same signature.
+sol! {
+    #[sol(deployed_bytecode="365f5f37365ffd")]
+    interface IThrower {
+        #[sol(abi)]
+        function Panic(uint256 code) external;
+        #[sol(abi)]
+        function InsufficientLiquidity(uint256 available) external;
+        #[sol(abi)]
+        function Unknown() external;
+    }
+}
+
+sol! {
+    #[sol(abi)]
+    #[derive(Debug)]
+    interface IPool {
+        error InsufficientLiquidity(uint256 available);
+    }
+}
+
 // Ephemeral lens that aggregates name/symbol/decimals in a single call
 // Source: examples/ERC20_metadata/TokenLens.sol
 sol! {
@@ -42,6 +128,8 @@ const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
 const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
 const DAI:  Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
 
+const HISTORICAL_BLOCK: u64 = 20_000_000;
+
 macro_rules! require_provider {
     () => {{
         let rpc_url = match env::var("RPC_URL") {
@@ -52,6 +140,24 @@ macro_rules! require_provider {
     }};
 }
 
+/// Provider answering each request with the next response queued in `asserter`
+fn mocked_provider(asserter: &Asserter) -> RootProvider {
+    ProviderBuilder::new().disable_recommended_fillers().connect_mocked_client(asserter.clone())
+}
+
+/// Multicall3 output of a batch of a single `decimals` call returning 6
+fn multicall_decimals() -> Bytes {
+    Bytes::from((vec![(true, Bytes::from(U256::from(6).abi_encode()))],).abi_encode_params())
+}
+
+/// Proxy output of a batch of a single `decimals` call returning 6
+fn proxy_decimals() -> Bytes {
+    // `Error(bytes)` envelope of the call
+    let envelope = (true, U256::from(100), false, Bytes::from(U256::from(6).abi_encode())).abi_encode_params();
+    let envelope = Bytes::from([&[0x08, 0xc3, 0x79, 0xa0][..], &(Bytes::from(envelope),).abi_encode_params()].concat());
+    Bytes::from((vec![envelope], U256::from(1_000)).abi_encode_params())
+}
+
 /// Batch multiple direct calls to well-known ERC20s in a single eth_call,
 /// asserting exact on-chain values.
 #[tokio::test]
@@ -66,7 +172,7 @@ async fn test_batch_direct_erc20_calls() {
         .with_call::<IERC20::symbolCall>(&USDC, ())
         .with_call::<IERC20::decimalsCall>(&USDC, ());
 
-    let results = lens.call().await;
+    let results = lens.call().await.unwrap();
 
     assert_eq!(results.len(), 6);
     assert!(results.iter().all(|r| r.success), "all calls should succeed");
@@ -93,7 +199,7 @@ async fn test_ephemeral_aggregator_lens() {
         .with_call::<ITokenLens::getTokenCall>(&lens_addr, (USDC,))
         .with_call::<ITokenLens::getTokenCall>(&lens_addr, (DAI,));
 
-    let results = lens.call().await;
+    let results = lens.call().await.unwrap();
 
     assert_eq!(results.len(), 3);
     assert!(results.iter().all(|r| r.success));
@@ -125,7 +231,7 @@ async fn test_revert_does_not_abort_batch() {
         .with_call::<IRevertLens::testFailCall>(&lens_addr, ())
         .with_call::<IERC20::symbolCall>(&USDC, ());
 
-    let results = lens.call().await;
+    let results = lens.call().await.unwrap();
 
     assert_eq!(results.len(), 3);
 
@@ -140,7 +246,7 @@ async fn test_revert_does_not_abort_batch() {
     assert_eq!(results[2].result[0].as_str().unwrap(), "USDC");
 }
 
-/// Each call's gas is measured independently. A lens call that makes three
+/// Each call's gas is measured independently and cold. A lens call that makes three
 /// sub-calls internally must consume more gas than a plain storage read.
 #[tokio::test]
 async fn test_gas_measured_per_call() {
@@ -149,18 +255,19 @@ async fn test_gas_measured_per_call() {
 
     let mut lens = Lens::new(&provider);
     lens.with_ephemeral(&lens_addr, ITokenLens::DEPLOYED_BYTECODE.clone())
+        .with_gas_measurement(GasMeasurement::Cold)
         // Simple: single storage slot read
         .with_call::<IERC20::decimalsCall>(&WETH, ())
         // Complex: three external sub-calls (name + symbol + decimals)
         .with_call::<ITokenLens::getTokenCall>(&lens_addr, (WETH,));
 
-    let results = lens.call().await;
+    let results = lens.call().await.unwrap();
 
     assert_eq!(results.len(), 2);
     assert!(results.iter().all(|r| r.success));
 
-    let simple_gas  = results[0].gas_used;
-    let complex_gas = results[1].gas_used;
+    let simple_gas  = results[0].cold_gas_used.unwrap();
+    let complex_gas = results[1].cold_gas_used.unwrap();
 
     assert!(simple_gas > U256::ZERO);
     assert!(
@@ -168,3 +275,1357 @@ async fn test_gas_measured_per_call() {
         "aggregated call ({complex_gas} gas) should use more than a single storage read ({simple_gas} gas)"
     );
 }
+
+/// An unreachable node must surface as a transport error instead of a panic.
+#[tokio::test]
+async fn test_transport_failure_is_an_error() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::nameCall>(&WETH, ());
+
+    let err = lens.call().await.unwrap_err();
+
+    assert!(matches!(err, LensError::Transport(_)), "unexpected error: {err}");
+}
+
+/// An output that isn't a batch result, e.g. from a node ignoring the overrides,
+/// is reported as malformed and not retried.
+#[tokio::test]
+async fn test_malformed_output_is_an_error() {
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+
+    // a retry would find no queued response and fail with a transport error
+    asserter.push_success(&Bytes::new());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_retry_policy(RetryPolicy::default().with_retries(2))
+        .with_call::<IERC20::nameCall>(&WETH, ());
+
+    let err = lens.call().await.unwrap_err();
+
+    assert!(matches!(err, LensError::MalformedOutput(ref data) if data.is_empty()), "unexpected error: {err}");
+}
+
+/// A call whose output doesn't decode, e.g. to an address without code, fails on its own.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_undecodable_output() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone());
+    let missing = lens.add_call::<IERC20::decimalsCall>(&Address::repeat_byte(0x4b), ());
+    let increment = lens.add_call::<ICounter::incrementCall>(&counter, ());
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    assert!(results[0].success && results[0].output.is_empty());
+    assert!(results[0].result.is_empty() && results[0].decode_error.is_some());
+    assert!(matches!(missing.get(&results), Err(CallFailure::Decode(_))));
+    assert_eq!(increment.get(&results).unwrap(), U256::from(1));
+    assert!(results[1].decode_error.is_none());
+}
+
+/// Typed handles decode each call's output to its `sol!` return type, and
+/// report reverted calls as failures.
+#[tokio::test]
+async fn test_typed_handles() {
+    let provider = require_provider!();
+    let lens_addr = Address::repeat_byte(0xca);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&lens_addr, IRevertLens::DEPLOYED_BYTECODE.clone());
+
+    let symbol   = lens.add_call::<IERC20::symbolCall>(&USDC, ());
+    let failing  = lens.add_call::<IRevertLens::testFailCall>(&lens_addr, ());
+    let decimals = lens.add_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(symbol.get(&results).unwrap(), "USDC");
+    assert!(matches!(failing.get(&results), Err(CallFailure::Reverted(RevertReason::Error(_)))));
+    assert_eq!(decimals.get(&results).unwrap(), 6);
+}
+
+/// A batch pinned to a historical block reads that block's state and
+/// reports the block number it ran at.
+#[tokio::test]
+async fn test_call_at_historical_block() {
+    let provider = require_provider!();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_block(BlockId::number(HISTORICAL_BLOCK))
+        .with_call::<IERC20::symbolCall>(&WETH, ())
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert!(results.iter().all(|r| r.success));
+    assert!(results.iter().all(|r| r.block_number == Some(HISTORICAL_BLOCK)));
+    assert_eq!(results[0].result[0].as_str().unwrap(), "WETH");
+}
+
+/// A batch split into several chunks returns its results in registration order.
+#[tokio::test]
+async fn test_chunked_batch_keeps_order() {
+    let provider = require_provider!();
+    let lens_addr = Address::repeat_byte(0xca);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_limits(BatchLimits::default().with_max_calls(2).with_concurrency(2))
+        .with_ephemeral(&lens_addr, ITokenLens::DEPLOYED_BYTECODE.clone())
+        .with_call::<IERC20::symbolCall>(&WETH, ())
+        .with_call::<IERC20::symbolCall>(&USDC, ())
+        .with_call::<IERC20::symbolCall>(&DAI, ())
+        .with_call::<ITokenLens::getTokenCall>(&lens_addr, (USDC,))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].result[0].as_str().unwrap(), "WETH");
+    assert_eq!(results[1].result[0].as_str().unwrap(), "USDC");
+    assert_eq!(results[2].result[0].as_str().unwrap(), "DAI");
+    // The ephemeral lens is installed in every chunk
+    assert_eq!(results[3].result[2].as_str().unwrap(), "USDC");
+    assert_eq!(results[4].result[0].as_uint().unwrap().0, U256::from(6u8));
+}
+
+/// Each limit closes a chunk on its own, a call exceeding a limit alone gets its own chunk.
+#[test]
+fn test_batch_limits_chunks() {
+    use alloy_ephemeral_lens::Call;
+
+    let function = Function::parse("decimals() returns (uint8)").unwrap();
+    let call = |argument: usize, gas: u64| {
+        let mut call = Call::new(function.clone(), USDC, Bytes::from(vec![1; argument]));
+        call.with_gas(U256::from(gas));
+        call
+    };
+    let small = call(4, 0);
+    let calls = [small.clone(), small.clone(), call(1_000, 0), small.clone(), call(4, 3_000_000), small.clone()];
+
+    let unlimited = BatchLimits::default();
+    assert_eq!(unlimited.chunks(&calls).len(), 1);
+    assert!(unlimited.chunks(&[]).is_empty());
+
+    let max_calls = BatchLimits::default().with_max_calls(4);
+    assert_eq!(max_calls.chunks(&calls), [0..4, 4..6]);
+
+    // execute header (68 bytes) and two calls with a 4 bytes argument (224 bytes each)
+    let max_calldata = BatchLimits::default().with_max_calldata_bytes(68 + 2 * 224);
+    assert_eq!(max_calldata.chunks(&calls), [0..2, 2..3, 3..5, 5..6]);
+
+    // calls without gas limit count as the default call gas
+    let max_gas = BatchLimits::default().with_max_gas(2_500_000);
+    assert_eq!(max_gas.chunks(&calls), [0..2, 2..4, 4..5, 5..6]);
+
+    let default_gas = BatchLimits::default().with_max_gas(2_500_000).with_default_call_gas(500_000);
+    assert_eq!(default_gas.chunks(&calls), [0..4, 4..5, 5..6]);
+}
+
+/// Results of a batch executed in several chunks come back in registration order.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_chunked_batch_keeps_order() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_limits(BatchLimits::default().with_max_calls(2));
+    for index in 0..5 {
+        let counter = Address::repeat_byte(0x50 + index);
+        lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+            .with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(index * 10))])
+            .with_call::<ICounter::incrementCall>(&counter, ());
+    }
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    let values: Vec<_> = results.iter().map(|r| r.result[0].as_uint().unwrap().0).collect();
+    assert_eq!(values, [1, 11, 21, 31, 41].map(U256::from));
+}
+
+/// Local execution reads on-chain state through a fork database over the provider.
+#[cfg(feature = "revm")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_fork() {
+    use alloy_ephemeral_lens::revm::database::{AlloyDB, WrapDatabaseAsync};
+
+    let provider = require_provider!();
+    let fork = WrapDatabaseAsync::new(AlloyDB::new(provider.clone(), BlockId::latest())).unwrap();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::nameCall>(&WETH, ())
+        .with_call::<IERC20::decimalsCall>(&WETH, ());
+
+    let results = lens.call_local(CacheDB::new(fork)).unwrap();
+
+    assert_eq!(results[0].result[0].as_str().unwrap(), "Wrapped Ether");
+    assert_eq!(results[1].result[0].as_uint().unwrap().0, U256::from(18));
+}
+
+/// An impersonated sender has contract code for the whole batch, as seen by the other calls.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_sender_has_code() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let context = Address::repeat_byte(0x47);
+    let code_size = Address::repeat_byte(0x4d);
+    let sender = Address::repeat_byte(0x48);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&context, IContext::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&code_size, ICodeSize::DEPLOYED_BYTECODE.clone())
+        .with_call::<ICodeSize::codeSizeCall>(&code_size, (sender,))
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_sender(sender)
+        .with_call::<ICodeSize::codeSizeCall>(&code_size, (sender,));
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    assert!(results.iter().all(|elt| elt.success));
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(64));
+    assert_eq!(results[0].result, results[2].result);
+}
+
+/// Splitting a batch with an impersonated sender doesn't change what the other calls see.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_chunked_sender() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let context = Address::repeat_byte(0x47);
+    let code_size = Address::repeat_byte(0x4d);
+    let sender = Address::repeat_byte(0x48);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&context, IContext::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&code_size, ICodeSize::DEPLOYED_BYTECODE.clone())
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_sender(sender)
+        .with_call::<ICodeSize::codeSizeCall>(&code_size, (sender,));
+    let unchunked = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    lens.with_limits(BatchLimits::default().with_max_calls(1));
+    let chunked = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    for (unchunked, chunked) in unchunked.iter().zip(&chunked) {
+        assert_eq!((unchunked.success, &unchunked.output), (chunked.success, &chunked.output));
+    }
+    assert_eq!(chunked.len(), 2);
+}
+
+/// Chunks of a batch without selected block all run at the latest block, resolved once.
+#[tokio::test]
+async fn test_chunks_share_latest_block() {
+    use alloy::rpc::types::Block;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+
+    // the latest block, then one `eth_call` per chunk
+    asserter.push_success(&Block::<alloy::rpc::types::Transaction>::default());
+    asserter.push_success(&decimals);
+    asserter.push_success(&decimals);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_limits(BatchLimits::default().with_max_calls(1))
+        .with_call::<IERC20::decimalsCall>(&USDC, ())
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.block_number.is_none()));
+    assert!(asserter.read_q().is_empty());
+}
+
+/// Calls can send ether funded by the proxy, and a gas limit makes a call
+/// fail on its own instead of consuming the whole batch gas.
+#[tokio::test]
+async fn test_call_value_and_gas_limit() {
+    let provider = require_provider!();
+    let lens_addr = Address::repeat_byte(0xca);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&lens_addr, ITokenLens::DEPLOYED_BYTECODE.clone())
+        .with_call::<IWETH::depositCall>(&WETH, ())
+        .with_value(U256::from(10).pow(U256::from(18)))
+        .with_call::<ITokenLens::getTokenCall>(&lens_addr, (WETH,))
+        .with_gas(5_000)
+        .with_call::<IERC20::decimalsCall>(&WETH, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert!(results[0].success, "payable deposit should succeed");
+    assert!(!results[1].success, "call should run out of gas");
+    assert!(results[1].gas_used < U256::from(10_000));
+    assert!(results[2].success);
+}
+
+/// In sequential mode a call sees the state changes of the previous calls,
+/// while isolated calls always read the initial state.
+#[tokio::test]
+async fn test_sequential_calls_keep_state() {
+    let provider = require_provider!();
+    let proxy = Address::repeat_byte(0x01);
+    let one_ether = U256::from(10).pow(U256::from(18));
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IWETH::depositCall>(&WETH, ())
+        .with_value(one_ether)
+        .with_call::<IWETH::balanceOfCall>(&WETH, (proxy,));
+
+    let isolated = lens.call().await.unwrap();
+    let sequential = lens.with_mode(ExecutionMode::Sequential).call().await.unwrap();
+
+    assert!(isolated.iter().chain(sequential.iter()).all(|r| r.success));
+    assert_eq!(isolated[1].result[0].as_uint().unwrap().0, U256::ZERO);
+    assert_eq!(sequential[1].result[0].as_uint().unwrap().0, one_ether);
+}
+
+/// Impersonated calls see the sender as `msg.sender`, with value forwarded.
+#[tokio::test]
+async fn test_call_with_sender() {
+    let provider = require_provider!();
+    let sender = address!("0x000000000000000000000000000000000000dEaD");
+    let one_ether = U256::from(10).pow(U256::from(18));
+
+    let mut lens = Lens::new(&provider);
+    lens.with_mode(ExecutionMode::Sequential)
+        .with_call::<IWETH::balanceOfCall>(&WETH, (sender,))
+        .with_call::<IWETH::depositCall>(&WETH, ())
+        .with_value(one_ether)
+        .with_sender(sender)
+        .with_call::<IWETH::balanceOfCall>(&WETH, (sender,));
+
+    let results = lens.call().await.unwrap();
+
+    assert!(results.iter().all(|r| r.success));
+    let before = results[0].result[0].as_uint().unwrap().0;
+    let after = results[2].result[0].as_uint().unwrap().0;
+    assert_eq!(after - before, one_ether);
+}
+
+/// A sender can't be impersonated when it also holds ephemeral code.
+#[tokio::test]
+async fn test_ephemeral_sender_is_rejected() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let sender = Address::repeat_byte(0x42);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&sender, ITokenLens::DEPLOYED_BYTECODE.clone())
+        .with_call::<IERC20::nameCall>(&WETH, ())
+        .with_sender(sender);
+
+    let err = lens.call().await.unwrap_err();
+
+    assert!(matches!(err, LensError::EphemeralSender(s) if s == sender), "unexpected error: {err}");
+}
+
+/// A call can't target an impersonated sender, whose code is replaced by the forwarder.
+#[tokio::test]
+async fn test_called_sender_is_rejected() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let sender = Address::repeat_byte(0x42);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::nameCall>(&WETH, ())
+        .with_sender(sender)
+        .with_call::<IERC20::nameCall>(&sender, ());
+
+    let err = lens.call().await.unwrap_err();
+
+    assert!(matches!(err, LensError::SenderCalled(s) if s == sender), "unexpected error: {err}");
+}
+
+/// Storage diffs only touch the given slots and merge with other overrides on the account.
+#[tokio::test]
+async fn test_account_overrides() {
+    let provider = require_provider!();
+    let holder = Address::repeat_byte(0x42);
+    let amount = U256::from(1234);
+    // WETH9 keeps balances in the mapping at slot 3
+    let slot = keccak256([B256::left_padding_from(holder.as_slice()), B256::with_last_byte(3)].concat());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_state_diff(&WETH, [(slot, amount.into())])
+        .with_nonce(&WETH, 7)
+        .with_call::<IWETH::balanceOfCall>(&WETH, (holder,))
+        .with_call::<IERC20::nameCall>(&WETH, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert!(results.iter().all(|r| r.success));
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, amount);
+    assert_eq!(results[1].result[0].as_str().unwrap(), "Wrapped Ether");
+}
+
+/// Block overrides are visible to the executed calls.
+#[tokio::test]
+async fn test_block_overrides() {
+    let provider = require_provider!();
+    let lens_addr = Address::repeat_byte(0x43);
+    let coinbase = Address::repeat_byte(0x44);
+    let prevrandao = B256::repeat_byte(0x45);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&lens_addr, IBlockLens::DEPLOYED_BYTECODE.clone())
+        .with_block_overrides(
+            BlockOverrides::default()
+                .with_time(4_000_000_000)
+                .with_number(U256::from(90_000_000))
+                .with_base_fee(U256::from(7))
+                .with_coinbase(coinbase)
+                .with_random(prevrandao)
+        )
+        .with_call::<IBlockLens::blockInfoCall>(&lens_addr, ());
+
+    let results = lens.call().await.unwrap();
+    let info = &results[0].result;
+
+    assert!(results[0].success);
+    assert_eq!(info[0].as_uint().unwrap().0, U256::from(4_000_000_000u64));
+    assert_eq!(info[1].as_uint().unwrap().0, U256::from(90_000_000));
+    assert_eq!(info[2].as_uint().unwrap().0, U256::from(7));
+    assert_eq!(info[3].as_address().unwrap(), coinbase);
+    assert_eq!(info[4].as_fixed_bytes().unwrap().0, prevrandao.as_slice());
+}
+
+/// The embedded EVM runs ephemeral contracts and both execution modes without a node.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_execution() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(10))])
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<ICounter::incrementCall>(&counter, ());
+
+    let isolated = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let sequential = lens.with_mode(ExecutionMode::Sequential)
+        .call_local(CacheDB::new(EmptyDB::new()))
+        .unwrap();
+
+    let values = |results: &[alloy_ephemeral_lens::CallResult]| -> Vec<U256> {
+        results.iter().map(|r| r.result[0].as_uint().unwrap().0).collect()
+    };
+    assert!(isolated.iter().chain(sequential.iter()).all(|r| r.success));
+    assert_eq!(values(&isolated), [U256::from(11), U256::from(11)]);
+    assert_eq!(values(&sequential), [U256::from(11), U256::from(12)]);
+}
+
+/// Local execution honours senders, values, block overrides and a moved proxy.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_execution_context() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let context = Address::repeat_byte(0x47);
+    let block = Address::repeat_byte(0x43);
+    let sender = Address::repeat_byte(0x48);
+
+    let proxy = Address::repeat_byte(0xfe);
+
+    let mut lens = Lens::new_with_proxy(&provider, proxy);
+    lens.with_ephemeral(&context, IContext::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&block, IBlockLens::DEPLOYED_BYTECODE.clone())
+        .with_block_overrides(BlockOverrides::default().with_time(4_000_000_000))
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_value(U256::from(5))
+        .with_sender(sender)
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_call::<IBlockLens::blockInfoCall>(&block, ());
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    assert!(results.iter().all(|r| r.success));
+    assert_eq!(results[0].result[0].as_address().unwrap(), sender);
+    assert_eq!(results[0].result[1].as_uint().unwrap().0, U256::from(5));
+    assert_eq!(results[1].result[0].as_address().unwrap(), proxy);
+    assert_eq!(results[2].result[0].as_uint().unwrap().0, U256::from(4_000_000_000u64));
+}
+
+/// Overrides, call targets and senders can't use the proxy address, which can be moved.
+#[tokio::test]
+async fn test_proxy_collision() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let proxy = Address::repeat_byte(0x01);
+
+    let mut overridden = Lens::new(&provider);
+    overridden.with_ephemeral(&proxy, ITokenLens::DEPLOYED_BYTECODE.clone())
+        .with_call::<IERC20::nameCall>(&WETH, ());
+    let mut called = Lens::new(&provider);
+    called.with_call::<IERC20::nameCall>(&proxy, ());
+    let mut sent = Lens::new(&provider);
+    sent.with_call::<IERC20::nameCall>(&WETH, ()).with_sender(proxy);
+
+    for lens in [overridden, called, sent] {
+        let err = lens.call().await.unwrap_err();
+        assert!(matches!(err, LensError::ProxyCollision(p) if p == proxy), "unexpected error: {err}");
+    }
+
+    let mut moved = Lens::new_with_proxy(&provider, Address::repeat_byte(0xfe));
+    moved.with_call::<IERC20::nameCall>(&proxy, ());
+    let err = moved.call().await.unwrap_err();
+    assert!(matches!(err, LensError::Transport(_)), "unexpected error: {err}");
+}
+
+/// Panics, registered custom errors and unknown revert data are told apart.
+#[cfg(feature = "revm")]
+#[test]
+fn test_revert_reasons() {
+    use alloy::sol_types::SolCall;
+
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let thrower = Address::repeat_byte(0x49);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&thrower, IThrower::DEPLOYED_BYTECODE.clone())
+        .with_error_abi(&IPool::abi::contract())
+        .with_call::<IThrower::PanicCall>(&thrower, (U256::from(0x11),))
+        .with_call::<IThrower::InsufficientLiquidityCall>(&thrower, (U256::from(7),))
+        .with_call::<IThrower::UnknownCall>(&thrower, ());
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let reasons: Vec<_> = results.iter().map(|r| r.revert.clone().unwrap()).collect();
+
+    assert!(matches!(&reasons[0], RevertReason::Panic(panic) if panic.code == U256::from(0x11)));
+    assert!(matches!(
+        &reasons[1],
+        RevertReason::Custom { name, args, .. } if name == "InsufficientLiquidity" && args[0].as_uint().unwrap().0 == U256::from(7)
+    ));
+    assert!(matches!(
+        reasons[1].decode_as::<IPool::IPoolErrors>(),
+        Some(IPool::IPoolErrors::InsufficientLiquidity(err)) if err.available == U256::from(7)
+    ));
+    assert_eq!(reasons[2], RevertReason::Raw(IThrower::UnknownCall {}.abi_encode().into()));
+}
+
+/// Raw outputs are kept on every result and can be decoded again later.
+#[tokio::test]
+async fn test_deferred_decoding() {
+    let provider = require_provider!();
+    let lens_addr = Address::repeat_byte(0xca);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&lens_addr, IRevertLens::DEPLOYED_BYTECODE.clone())
+        .with_call::<IERC20::decimalsCall>(&USDC, ())
+        .with_call::<IRevertLens::testFailCall>(&lens_addr, ());
+
+    let results = lens.call().await.unwrap();
+
+    let decimals = Function::parse("decimals() returns (uint256)").unwrap();
+    assert_eq!(results[0].decode_with(&decimals).unwrap()[0].as_uint().unwrap().0, U256::from(6));
+    assert_eq!(results[0].decode_as::<IERC20::decimalsCall>().unwrap(), 6);
+    assert!(!results[1].success);
+    assert_eq!(results[1].output, results[1].revert.as_ref().unwrap().data());
+}
+
+/// Calls built at runtime from a JSON ABI go through the same machinery as `sol!` calls.
+#[tokio::test]
+async fn test_dynamic_abi_calls() {
+    let provider = require_provider!();
+    let abi: JsonAbi = serde_json::from_str(r#"[
+        {"type":"function","name":"symbol","inputs":[],"outputs":[{"name":"","type":"string"}],"stateMutability":"view"},
+        {"type":"function","name":"balanceOf","inputs":[{"name":"owner","type":"address"}],"outputs":[{"name":"","type":"uint256"}],"stateMutability":"view"}
+    ]"#).unwrap();
+    let symbol = Function::parse("symbol() returns (string)").unwrap();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_dyn_call(&DAI, &symbol, &[]).unwrap()
+        .with_abi_call(&WETH, &abi, "balanceOf", &[DynSolValue::Address(Address::ZERO)]).unwrap();
+
+    assert!(matches!(lens.with_dyn_call(&WETH, &symbol, &[DynSolValue::Bool(true)]), Err(LensError::Encode(_))));
+    assert!(matches!(lens.with_abi_call(&WETH, &abi, "balanceOf", &[]), Err(LensError::FunctionNotFound(_))));
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].result[0].as_str().unwrap(), "DAI");
+    assert!(results[1].success);
+    assert!(results[1].result[0].as_uint().is_some());
+}
+
+
+/// Human-readable signatures drive encoding and decoding, with string or typed arguments.
+#[tokio::test]
+async fn test_signature_calls() {
+    let provider = require_provider!();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_signature_call(&USDC, "decimals()(uint8)", Vec::<&str>::new()).unwrap()
+        .with_signature_call(&WETH, "balanceOf(address)(uint256)", ["0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"]).unwrap()
+        .with_signature_call(&WETH, "balanceOf(address) returns (uint256)", [DynSolValue::Address(WETH)]).unwrap();
+
+    assert!(matches!(
+        lens.with_signature_call(&WETH, "balanceOf(address)(uint256)", ["not an address"]),
+        Err(LensError::Encode(_))
+    ));
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+    assert_eq!(results[1].result, results[2].result);
+}
+
+
+/// Reported gas is the callee's own execution gas, out of gas calls are flagged.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_gas_accounting() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+    let spinner = Address::repeat_byte(0x4a);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&spinner, ILoop::DEPLOYED_BYTECODE.clone())
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<ILoop::spinCall>(&spinner, ())
+        .with_gas(50_000);
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    // cold SLOAD (2100) + zero to non-zero SSTORE (20000) + 26 for the other opcodes
+    assert_eq!(results[0].gas_used, U256::from(22_126));
+    assert!(results[0].success && !results[0].out_of_gas);
+    assert_eq!(results[1].gas_used, U256::from(50_000));
+    assert!(!results[1].success && results[1].out_of_gas);
+    assert_eq!(results[0].eth_call_gas, results[1].eth_call_gas);
+    assert!(results[0].eth_call_gas > U256::from(21_000 + 22_126 + 50_000));
+}
+
+/// Gas reported by the proxy matches a direct execution of each callee, and the gas of the
+/// whole `eth_call` a direct execution of the proxy: fails when the gas constants of
+/// `contracts/Proxy.easm` diverge from its code.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_gas_matches_direct_execution() {
+    use alloy::{primitives::TxKind, sol_types::SolCall};
+    use revm::{
+        bytecode::Bytecode,
+        context::TxEnv,
+        context_interface::result::ExecutionResult,
+        state::AccountInfo,
+        primitives::hardfork::SpecId,
+        Context, ExecuteEvm, MainBuilder, MainContext,
+    };
+
+    sol! {
+        interface IProxy {
+            struct CallArgument { address callee; bytes argument; uint256 value; uint256 gas; }
+            function execute(CallArgument[] calldata calls) external returns (bytes[] memory, uint256);
+        }
+    }
+
+    fn transact(db: CacheDB<EmptyDB>, spec: SpecId, to: Address, data: Bytes, value: U256) -> ExecutionResult {
+        let mut evm = Context::mainnet()
+            .with_db(db)
+            .modify_cfg_chained(|cfg| {
+                cfg.spec = spec;
+                cfg.disable_balance_check = true;
+            })
+            .build_mainnet();
+        let tx = TxEnv { kind: TxKind::Call(to), data, value, gas_limit: 1_000_000, ..Default::default() };
+        evm.transact(tx).unwrap().result
+    }
+
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let proxy = Address::repeat_byte(0x01);
+    let counter = Address::repeat_byte(0x46);
+    let context = Address::repeat_byte(0x47);
+    let empty = Address::repeat_byte(0x4c);
+    let value = U256::from(7);
+
+    let mut db = CacheDB::new(EmptyDB::new());
+    db.insert_account_info(counter, AccountInfo::from_bytecode(Bytecode::new_raw(ICounter::DEPLOYED_BYTECODE.clone())));
+    db.insert_account_info(context, AccountInfo::from_bytecode(Bytecode::new_raw(IContext::DEPLOYED_BYTECODE.clone())));
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_value(value)
+        .with_call::<IContext::contextCall>(&empty, ())
+        .with_value(value);
+
+    let results = lens.call_local(db.clone()).unwrap();
+
+    let calls = [
+        (counter, ICounter::incrementCall {}.abi_encode(), U256::ZERO),
+        (context, IContext::contextCall {}.abi_encode(), value),
+        (empty, IContext::contextCall {}.abi_encode(), value),
+    ];
+    for ((callee, argument, value), result) in calls.iter().zip(&results) {
+        // before EIP-7623, the calldata floor would hide the execution gas of small calls
+        let direct = transact(db.clone(), SpecId::CANCUN, *callee, argument.clone().into(), *value);
+        let intrinsic = 21_000 + argument.iter().map(|elt| if *elt == 0 { 4 } else { 16 }).sum::<u64>();
+        assert!(direct.is_success() && result.success);
+        assert_eq!(result.gas_used, U256::from(direct.gas_used() - intrinsic), "callee {callee}");
+    }
+
+    let arguments = calls.iter()
+        .map(|(callee, argument, value)| IProxy::CallArgument {
+            callee: *callee,
+            argument: argument.clone().into(),
+            value: *value,
+            gas: U256::ZERO,
+        })
+        .collect();
+    let mut db = db;
+    db.insert_account_info(
+        proxy,
+        AccountInfo::from_bytecode(Bytecode::new_raw(Bytes::from_static(include_bytes!(concat!(env!("OUT_DIR"), "/Proxy.bin")))))
+            .with_balance(value * U256::from(2)),
+    );
+    let direct = transact(db, SpecId::PRAGUE, proxy, IProxy::executeCall::new((arguments,)).abi_encode().into(), U256::ZERO);
+    assert!(direct.is_success());
+    assert!(results.iter().all(|elt| elt.eth_call_gas == U256::from(direct.gas_used())));
+
+    // the gas forwarded without limit is rebuilt exactly, so an exhausted call has none left,
+    // twice as a one-off error can vanish in the rounding of the 63/64 rule
+    let spinner = Address::repeat_byte(0x4a);
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&spinner, ILoop::DEPLOYED_BYTECODE.clone())
+        .with_call::<ILoop::spinCall>(&spinner, ())
+        .with_call::<ILoop::spinCall>(&spinner, ());
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    assert!(results.iter().all(|elt| !elt.success && elt.out_of_gas));
+}
+
+/// Calls are measured cold and warm on the initial state,
+/// sequential calls depending on the previous ones are left unmeasured.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_gas_measurement() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+    let context = Address::repeat_byte(0x47);
+    let sender = Address::repeat_byte(0x48);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&context, IContext::DEPLOYED_BYTECODE.clone())
+        .with_mode(ExecutionMode::Sequential)
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_value(U256::from(7))
+        .with_sender(sender);
+
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    assert_eq!(results[0].gas_used, U256::from(22_126));
+    assert!(results[1].gas_used < results[0].gas_used);
+    assert!(results.iter().all(|elt| elt.cold_gas_used.is_none() && elt.warm_gas_used.is_none()));
+
+    lens.with_gas_measurement(GasMeasurement::Cold);
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    assert_eq!(results[0].cold_gas_used, Some(U256::from(22_126)));
+    // measured on the initial state, the second increment doesn't return what it actually does
+    assert_eq!(results[1].cold_gas_used, None);
+    assert!(results[1].gas_used < U256::from(22_126));
+    assert!(results[2].cold_gas_used.is_some());
+    assert!(results.iter().all(|elt| elt.warm_gas_used.is_none()));
+
+    lens.with_gas_measurement(GasMeasurement::ColdAndWarm);
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    assert!(results.iter().all(|elt| elt.success));
+    assert!(results[1].cold_gas_used.is_none() && results[1].warm_gas_used.is_none());
+    // warm SLOAD (100) + SSTORE to a slot already written in the transaction (100) + 26
+    assert_eq!(results[0].warm_gas_used, Some(U256::from(226)));
+    assert!(results[2].warm_gas_used.unwrap() < results[2].cold_gas_used.unwrap());
+
+    lens.with_mode(ExecutionMode::Isolated);
+    let results = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    assert!(results.iter().all(|elt| elt.cold_gas_used == Some(elt.gas_used)));
+}
+
+/// A batch exported as JSON is reloaded identically against another provider.
+#[tokio::test]
+async fn test_plan_roundtrip() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let other = ProviderBuilder::new().connect_http("http://127.0.0.1:2".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+
+    let mut lens = Lens::new_with_proxy(&provider, Address::repeat_byte(0xfe));
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(41))])
+        .with_block(BlockId::number(19_000_000))
+        .with_block_overrides(BlockOverrides::default().with_time(1_800_000_000))
+        .with_limits(BatchLimits::default().with_max_calls(2))
+        .with_mode(ExecutionMode::Sequential)
+        .with_gas_measurement(GasMeasurement::ColdAndWarm)
+        .with_error_abi(&IPool::abi::contract())
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_value(U256::from(7))
+        .with_gas(100_000)
+        .with_sender(Address::repeat_byte(0x48));
+    lens.with_signature_call(&WETH, "balanceOf(address)(uint256)", ["0x000000000000000000000000000000000000dEaD"]).unwrap();
+
+    let json = serde_json::to_string(&lens.plan()).unwrap();
+    let plan: LensPlan = serde_json::from_str(&json).unwrap();
+    assert_eq!(plan, lens.plan());
+    assert_eq!(plan.calls.len(), 2);
+
+    let reloaded = Lens::from_plan(&other, plan);
+    assert_eq!(reloaded.plan(), lens.plan());
+}
+
+/// A reloaded plan executes to the same results as the original batch.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_plan_replay() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(41))])
+        .with_mode(ExecutionMode::Sequential)
+        .with_call::<ICounter::incrementCall>(&counter, ());
+    let handle = lens.add_call::<ICounter::incrementCall>(&counter, ());
+
+    let plan: LensPlan = serde_json::from_str(&serde_json::to_string(&lens.plan()).unwrap()).unwrap();
+    let replayed = Lens::from_plan(&provider, plan).call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let original = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+
+    assert_eq!(handle.get(&replayed).unwrap(), U256::from(43));
+    for (replayed, original) in replayed.iter().zip(&original) {
+        assert_eq!(replayed.result, original.result);
+        assert_eq!(replayed.gas_used, original.gas_used);
+    }
+}
+
+/// Multicall3 serves plain reads with the same result shape, and rejects what needs the proxy.
+#[tokio::test]
+async fn test_multicall3_backend() {
+    let provider = require_provider!();
+    let unknown = Function::parse("unknown() returns (uint256)").unwrap();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::decimalsCall>(&USDC, ())
+        .with_dyn_call(&USDC, &unknown, &[]).unwrap();
+
+    let expected = lens.call().await.unwrap();
+    let results = lens.with_backend(Backend::Multicall3).call().await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].result, expected[0].result);
+    assert!(results[0].success && results[0].gas_used.is_zero());
+    assert!(!results[1].success);
+    assert_eq!(results[1].revert, expected[1].revert);
+}
+
+/// Batches needing the proxy are rejected by the Multicall3 backend before anything is sent.
+#[tokio::test]
+async fn test_multicall3_unsupported() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_sender(Address::repeat_byte(0x48));
+    assert!(matches!(
+        lens.call().await,
+        Err(LensError::Unsupported { backend: Backend::Multicall3, feature: "call senders" })
+    ));
+
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone());
+    assert!(matches!(
+        lens.call().await,
+        Err(LensError::Unsupported { feature: "state overrides", .. })
+    ));
+}
+
+/// Probe requests rejected by the node are reported as missing features.
+#[tokio::test]
+async fn test_probe_capabilities() {
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+
+    // state override call, block override call, PUSH0 deployment, head, historical balance
+    asserter.push_success(&B256::from(U256::from(42)));
+    asserter.push_success(&B256::from(U256::from(1_700_000_000)));
+    asserter.push_failure_msg("invalid opcode: PUSH0");
+    asserter.push_success(&U256::from(20_000_000));
+    asserter.push_failure_msg("missing trie node");
+
+    let capabilities = Lens::new(&provider).probe(10_000).await.unwrap();
+
+    assert!(capabilities.state_overrides);
+    assert!(!capabilities.block_overrides);
+    assert!(!capabilities.push0);
+    assert!(!capabilities.historical_state);
+    assert_eq!(capabilities.historical_depth, 10_000);
+    assert_eq!(capabilities.backend(), Backend::Multicall3);
+}
+
+/// A node without transport fails the probe instead of reporting missing features.
+#[tokio::test]
+async fn test_probe_transport_failure() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+
+    assert!(matches!(Lens::new(&provider).probe(0).await, Err(LensError::Transport(_))));
+}
+
+/// Each new block yields the batch results pinned to that block.
+#[tokio::test]
+async fn test_watch_blocks() {
+    use alloy::consensus::BlockHeader;
+    use futures::StreamExt;
+
+    let provider = require_provider!();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let mut blocks = Box::pin(lens.watch().await.unwrap());
+    let (header, results) = blocks.next().await.unwrap().unwrap();
+
+    assert_eq!(results[0].block_number, Some(header.number()));
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+}
+
+/// Headers superseded while waiting for a run are skipped, only the newest one is executed.
+#[tokio::test]
+async fn test_watch_coalesces_headers() {
+    use alloy::rpc::types::Header;
+    use futures::{channel::mpsc, StreamExt};
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+
+    let (sender, headers) = mpsc::unbounded();
+    for number in 100..200 {
+        let mut header: Header = Header { hash: B256::with_last_byte(number as u8), ..Default::default() };
+        header.inner.number = number;
+        sender.unbounded_send(header).unwrap();
+    }
+    drop(sender);
+    asserter.push_success(&decimals);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let updates: Vec<_> = lens.watch_headers(headers).unwrap().collect().await;
+
+    assert_eq!(updates.len(), 1);
+    let (header, results) = updates[0].as_ref().unwrap();
+    assert_eq!((header.number, results[0].block_number), (199, Some(199)));
+    assert!(asserter.read_q().is_empty());
+}
+
+/// Watching blocks needs a subscription capable provider.
+#[tokio::test]
+async fn test_watch_requires_pubsub() {
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+
+    assert!(matches!(Lens::new(&provider).watch().await, Err(LensError::Transport(_))));
+}
+
+/// Each block of a series is reported on its own, transport errors are retried after the policy delay.
+#[tokio::test]
+async fn test_block_series() {
+    use std::time::{Duration, Instant};
+    use alloy::rpc::types::Block;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+    let block = Block::<alloy::rpc::types::Transaction>::default();
+
+    // blocks 100, 102 then 104, executed one at a time, each resolved to its hash on every attempt:
+    // a garbled response to block 102 is retried, the node rejecting block 104 isn't
+    asserter.push_success(&block);
+    asserter.push_success(&decimals);
+    asserter.push_success(&block);
+    asserter.push_success(&"garbled");
+    asserter.push_success(&block);
+    asserter.push_success(&decimals);
+    asserter.push_success(&block);
+    asserter.push_failure_msg("missing trie node");
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_retry_policy(RetryPolicy::default().with_backoff(Duration::from_millis(50)))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let series = BlockSeries::new(100..=105, 2).with_retries(1).with_concurrency(1);
+    let start = Instant::now();
+    let results = lens.call_series(&series).await.unwrap();
+
+    // the retry of block 102
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    assert_eq!(results.iter().map(|elt| elt.0).collect::<Vec<_>>(), vec![100, 102, 104]);
+    for (block, results) in &results[..2] {
+        let results = results.as_ref().unwrap();
+        assert_eq!(results[0].block_number, Some(*block));
+        assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+    }
+    assert!(matches!(&results[2].1, Err(err) if !err.is_retryable()));
+    assert!(asserter.read_q().is_empty());
+}
+
+/// Blocks of a series timing out are retried.
+#[tokio::test]
+async fn test_block_series_timeout() {
+    use std::time::{Duration, Instant};
+
+    // Accepts connections and never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let provider = ProviderBuilder::new().connect_http(url.parse().unwrap());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_retry_policy(RetryPolicy::default().with_timeout(Duration::from_millis(50)).with_backoff(Duration::from_millis(1)))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let series = BlockSeries::new(100..=100, 1).with_retries(2);
+    let start = Instant::now();
+    let results = lens.call_series(&series).await.unwrap();
+
+    assert!(matches!(results[0].1, Err(LensError::Timeout(_))));
+    assert!(start.elapsed() >= Duration::from_millis(150));
+}
+
+/// Blocks of a series are pinned to their hash, so a second run is served from the cache.
+#[tokio::test]
+async fn test_block_series_cache() {
+    use std::sync::Arc;
+    use alloy::rpc::types::Block;
+    use alloy_ephemeral_lens::LruCache;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+    let blocks = [1, 2].map(|index| {
+        let mut block = Block::<alloy::rpc::types::Transaction>::default();
+        block.header.hash = B256::repeat_byte(index);
+        block
+    });
+    let cache = Arc::new(LruCache::new(16));
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_cache(cache.clone())
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+    let series = BlockSeries::new(100..=101, 1).with_concurrency(1);
+
+    asserter.push_success(&blocks[0]);
+    asserter.push_success(&decimals);
+    asserter.push_success(&blocks[1]);
+    asserter.push_success(&decimals);
+    let first = lens.call_series(&series).await.unwrap();
+
+    // only the blocks are resolved again
+    asserter.push_success(&blocks[0]);
+    asserter.push_success(&blocks[1]);
+    let second = lens.call_series(&series).await.unwrap();
+
+    assert_eq!(cache.len(), 2);
+    for ((block, first), (_, second)) in first.iter().zip(&second) {
+        let (first, second) = (first.as_ref().unwrap(), second.as_ref().unwrap());
+        assert_eq!(first[0].result, second[0].result);
+        assert_eq!(second[0].block_number, Some(*block));
+    }
+    assert!(asserter.read_q().is_empty());
+}
+
+/// Both lenses of a diff run at the latest block, resolved once.
+#[tokio::test]
+async fn test_diff_with_shares_latest_block() {
+    use alloy::rpc::types::Block;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+
+    // the latest block, then one `eth_call` per lens
+    asserter.push_success(&Block::<alloy::rpc::types::Transaction>::default());
+    asserter.push_success(&decimals);
+    asserter.push_success(&decimals);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+    let mut other = Lens::new(&provider);
+    other.with_backend(Backend::Multicall3)
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let diffs = lens.diff_with(&other).await.unwrap();
+
+    assert!(diffs.is_empty());
+    assert!(asserter.read_q().is_empty());
+}
+
+/// Diffs report changed output fields and success transitions, unchanged calls are left out.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_diff() {
+    use alloy_ephemeral_lens::CallDiff;
+
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let counter = Address::repeat_byte(0x46);
+    let context = Address::repeat_byte(0x47);
+    let patched = Address::repeat_byte(0x49);
+
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&counter, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(41))])
+        .with_ephemeral(&context, IContext::DEPLOYED_BYTECODE.clone())
+        .with_ephemeral(&patched, ICounter::DEPLOYED_BYTECODE.clone())
+        .with_call::<ICounter::incrementCall>(&counter, ())
+        .with_call::<IContext::contextCall>(&context, ())
+        .with_call::<ICounter::incrementCall>(&patched, ());
+
+    let mut other = Lens::from_plan(&provider, lens.plan());
+    other.with_state_diff(&counter, [(B256::ZERO, B256::with_last_byte(99))])
+        .with_ephemeral(&patched, IThrower::DEPLOYED_BYTECODE.clone());
+
+    let before = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let after = other.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let diffs = CallDiff::between(&before, &after).unwrap();
+
+    assert_eq!(diffs.len(), 2);
+    assert_eq!(diffs[0].index, 0);
+    assert_eq!(diffs[0].fields.len(), 1);
+    assert_eq!(diffs[0].fields[0].path, vec![0]);
+    assert_eq!(diffs[0].fields[0].before, DynSolValue::from(U256::from(42)));
+    assert_eq!(diffs[0].fields[0].after, DynSolValue::from(U256::from(100)));
+    assert!(diffs[0].success.is_none() && diffs[0].gas_used.is_none());
+
+    assert_eq!(diffs[1].index, 2);
+    assert_eq!(diffs[1].success, Some((true, false)));
+    assert!(matches!(diffs[1].revert, Some((None, Some(RevertReason::Raw(_))))));
+    assert!(diffs[1].fields.is_empty() && diffs[1].gas_used.is_some());
+}
+
+/// Diffs report changed raw outputs and decoding errors, even when the decoded values match,
+/// and batches of different lengths aren't compared.
+#[cfg(feature = "revm")]
+#[test]
+fn test_local_diff_output_and_decoding() {
+    use alloy::primitives::bytes;
+    use alloy_ephemeral_lens::CallDiff;
+
+    let provider = ProviderBuilder::new().connect_http("http://127.0.0.1:1".parse().unwrap());
+    let (padded, emptied) = (Address::repeat_byte(0x4e), Address::repeat_byte(0x4f));
+
+    // both return 6
+    let mut lens = Lens::new(&provider);
+    lens.with_ephemeral(&padded, bytes!("60065f5260205ff3"))
+        .with_ephemeral(&emptied, bytes!("60065f5260205ff3"))
+        .with_call::<IERC20::decimalsCall>(&padded, ())
+        .with_call::<IERC20::decimalsCall>(&emptied, ());
+
+    // returns 6 followed by a zero word, then nothing
+    let mut other = Lens::from_plan(&provider, lens.plan());
+    other.with_ephemeral(&padded, bytes!("60065f5260405ff3"))
+        .with_ephemeral(&emptied, bytes!("00"));
+
+    let before = lens.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let after = other.call_local(CacheDB::new(EmptyDB::new())).unwrap();
+    let diffs = CallDiff::between(&before, &after).unwrap();
+
+    assert_eq!(diffs.len(), 2);
+    assert!(diffs[0].fields.is_empty() && diffs[0].decode_error.is_none());
+    assert_eq!(diffs[0].output.as_ref().unwrap().1.len(), 64);
+
+    assert!(matches!(diffs[1].decode_error, Some((None, Some(_)))));
+    assert_eq!(diffs[1].fields.len(), 1);
+    assert!(diffs[1].fields[0].path.is_empty());
+    assert_eq!(diffs[1].fields[0].after, DynSolValue::Tuple(vec![]));
+
+    // batches of different lengths can't be compared
+    let err = CallDiff::between(&before, &after[..1]).unwrap_err();
+    assert!(matches!(err, LensError::ResultCountMismatch { expected: 2, actual: 1 }), "unexpected error: {err}");
+}
+
+/// Results that didn't change between two blocks produce no diff.
+#[tokio::test]
+async fn test_diff_blocks() {
+    let provider = require_provider!();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let diffs = lens.diff_blocks(BlockId::number(19_000_000), BlockId::number(19_000_100)).await.unwrap();
+    assert!(diffs.is_empty());
+}
+
+/// Transport errors are retried, rejected requests fall back to the next provider right away.
+#[tokio::test]
+async fn test_retry_and_fallback() {
+    use std::time::Duration;
+
+    let decimals = multicall_decimals();
+    let (primary, rejecting, fallback) = (Asserter::new(), Asserter::new(), Asserter::new());
+
+    // the primary has no response queued, each attempt fails with a transport error
+    rejecting.push_failure_msg("state overrides are not supported");
+    fallback.push_success(&decimals);
+
+    let mut lens = Lens::new(mocked_provider(&primary));
+    lens.with_backend(Backend::Multicall3)
+        .with_fallback(mocked_provider(&rejecting))
+        .with_fallback(mocked_provider(&fallback))
+        .with_retry_policy(RetryPolicy::default().with_retries(2).with_backoff(Duration::from_millis(1)))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+    assert_eq!(results[0].provider, 2);
+    // three attempts on the primary, one on the rejecting provider, one on the last fallback
+    assert_eq!(results[0].attempts, 5);
+}
+
+/// A node ignoring the state overrides isn't retried, the next provider answers instead.
+#[tokio::test]
+async fn test_malformed_output_falls_back() {
+
+    let (primary, fallback) = (Asserter::new(), Asserter::new());
+
+    // without the proxy code, the call to its address returns nothing
+    primary.push_success(&Bytes::new());
+    fallback.push_success(&proxy_decimals());
+
+    let mut lens = Lens::new(mocked_provider(&primary));
+    lens.with_fallback(mocked_provider(&fallback))
+        .with_retry_policy(RetryPolicy::default().with_retries(2))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+    assert_eq!((results[0].provider, results[0].attempts), (1, 2));
+}
+
+/// Attempts exceeding the timeout fail with a timeout error.
+#[tokio::test]
+async fn test_retry_timeout() {
+    use std::time::Duration;
+
+    // Accepts connections and never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let provider = ProviderBuilder::new().connect_http(url.parse().unwrap());
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_retry_policy(RetryPolicy::default().with_timeout(Duration::from_millis(50)).with_retries(1))
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    assert!(matches!(lens.call().await, Err(LensError::Timeout(_))));
+}
+
+/// The least recently used output is evicted once the cache is full.
+#[test]
+fn test_lru_cache() {
+    use alloy_ephemeral_lens::{CacheKey, LensCache, LruCache};
+
+    let key = |byte| CacheKey::new(B256::repeat_byte(byte), Bytes::new(), &Default::default(), None);
+    let cache = LruCache::new(2);
+
+    cache.insert(key(1), Bytes::from_static(&[1]));
+    cache.insert(key(2), Bytes::from_static(&[2]));
+    assert_eq!(cache.get(&key(1)), Some(Bytes::from_static(&[1])));
+    cache.insert(key(3), Bytes::from_static(&[3]));
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&key(2)).is_none());
+    assert!(cache.get(&key(1)).is_some() && cache.get(&key(3)).is_some());
+
+    // Overrides are part of the key
+    let overrides = [(WETH, Default::default())].into_iter().collect();
+    assert_ne!(CacheKey::new(B256::ZERO, Bytes::new(), &overrides, None), key(0));
+}
+
+/// Batches pinned to a block hash are served from the cache, `latest` ones never are.
+#[tokio::test]
+async fn test_lens_cache() {
+    use std::sync::Arc;
+    use alloy::rpc::types::Block;
+    use alloy_ephemeral_lens::LruCache;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let decimals = multicall_decimals();
+    let cache = Arc::new(LruCache::new(16));
+
+    let mut lens = Lens::new(&provider);
+    lens.with_backend(Backend::Multicall3)
+        .with_cache(cache.clone())
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    // latest: both runs reach the node
+    asserter.push_success(&decimals);
+    asserter.push_success(&decimals);
+    lens.call().await.unwrap();
+    lens.call().await.unwrap();
+    assert!(cache.is_empty());
+
+    // pinned: the block is resolved on each run, the second `eth_call` is cached
+    let block = Block::<alloy::rpc::types::Transaction>::default();
+    lens.with_block(BlockId::number(19_000_000));
+    asserter.push_success(&block);
+    asserter.push_success(&decimals);
+    asserter.push_success(&block);
+    let first = lens.call().await.unwrap();
+    let second = lens.call().await.unwrap();
+
+    assert_eq!(cache.len(), 1);
+    assert_eq!(first[0].result, second[0].result);
+    assert!(asserter.read_q().is_empty());
+}
+
+/// On the proxy backend the state overrides are part of the cache key, changing one misses the cache.
+#[tokio::test]
+async fn test_lens_cache_overrides() {
+    use std::sync::Arc;
+    use alloy::rpc::types::Block;
+    use alloy_ephemeral_lens::LruCache;
+
+    let asserter = Asserter::new();
+    let provider = mocked_provider(&asserter);
+    let cache = Arc::new(LruCache::new(16));
+    let block = Block::<alloy::rpc::types::Transaction>::default();
+
+    let mut lens = Lens::new(&provider);
+    lens.with_block(BlockId::number(19_000_000))
+        .with_cache(cache.clone())
+        .with_call::<IERC20::decimalsCall>(&USDC, ());
+
+    // same overrides: the second `eth_call` is cached
+    asserter.push_success(&block);
+    asserter.push_success(&proxy_decimals());
+    asserter.push_success(&block);
+    lens.call().await.unwrap();
+    lens.call().await.unwrap();
+    assert_eq!(cache.len(), 1);
+    assert!(asserter.read_q().is_empty());
+
+    // a new balance override changes the key, the node is asked again
+    lens.with_balance(&WETH, U256::from(1));
+    asserter.push_success(&block);
+    asserter.push_success(&proxy_decimals());
+    let results = lens.call().await.unwrap();
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(results[0].result[0].as_uint().unwrap().0, U256::from(6));
+    assert!(asserter.read_q().is_empty());
+}